@@ -1,23 +1,58 @@
 # ------------------------------------------------
 #   Input params
 # ------------------------------------------------
-l=8                 # number of sites
-beta=$((l*6))       # inverse temperature (large enough to approximate the ground state)
+l=8                 # number of sites of a chain (the length l for the other lattices)
+beta=$((l*6))       # inverse temperature of each copy (large enough to approximate the ground state for mode=gs)
 J=1                 # coupling strength of the Ising interaction
 h=1                 # strength of the magnetic fields
 num_thm=20000       # number of MC steps for thermalization
 num_stat=50000      # number of MC samples for each binning data
 num_bins=5          # number of bins
 seed=2025           # seed for PRNG
+mode=gs             # "gs" (ground state projection) or "ft" (finite temperature)
+bc=obc              # boundary conditions: "obc" or "pbc"
+lattice=chain       # "chain", "ladder" (l x ly), "square" (l x ly) or "triangular" (l x ly)
+ly=1                # width in the second direction (number of legs for a ladder)
 
 # -------------------------------------
 #   Initialize "/data" and compile 
 # -------------------------------------
 cargo build --release   
-path="./data/l"$l"_beta"$beta"_J"$J"_h"$h
-mkdir $path
+path="./data/"$lattice"_l"$l"_ly"$ly"_beta"$beta"_J"$J"_h"$h"_"$bc"_"$mode
 
 # ---------------------------
 #   Run the program
 # ---------------------------
-./target/release/bell_qmc_tfim_1d $l $beta $J $h $num_thm $num_stat $num_bins $path $seed
\ No newline at end of file
+# The positional arguments may also come from a config file (see "example.conf"):
+#   bell_qmc_tfim_1d --config run.conf [--key value ...]   (the options override the file)
+# Optional site- and bond-dependent couplings (exclusive):
+#   --couplings <file>      lines "J <b> <value>" / "h <i> <value>" overriding J and h
+#   --disorder box|log:W    random J_b and h_i, seeded by --disorder-seed (default: derived from seed)
+#   --realizations N        with --disorder: average over N realizations, each in "realization_r/"
+# Optional regions for the Renyi-2 entropies (sites "i", intervals "a..b" or "a..=b"):
+#   --regions "A=0..4;B=0,2,5"  one column of "renyi2_regions.dat" per region
+#   --regions-file <file>       one region "name=items" per line
+# Optional correlators:
+#   --ref-site r            reference site of "zz_corr_2.dat" and "xx_corr_2.dat" (default 0)
+#   --corr yy,xy,xz         further squared correlators tr(rho P_r Q_j)^2, each in "pq_corr_2.dat"
+#   --pauli-strings <file>  tr(rho P)^2 of Pauli strings, one per line ("X0 Z1 Z2 X3" or "ZZZZ")
+#   --corr-matrix true      all-pairs L x L matrices and their translation averages C(d)
+# Optional exact reference (up to 14 sites for gs, 10 for ft; not with --realizations, --sweep or --chains):
+#   --exact true            exact diagonalization of the same observables, compared in "exact.dat"
+#   --free-fermion true     exact ground state of a chain (any l, gs only) in "free_fermion/", in the format
+#                           of purity.dat, renyi2_ee.dat, zz_corr_2.dat, xx_corr_2.dat and renyi2_profile.dat
+# Optional restart (not with --realizations):
+#   --checkpoint <file>     save the state after every bin, resume from <file> if it exists
+#   --max-runtime 12h       stop (with a checkpoint) before the next bin would exceed it
+# Optional parallel chains (not with --realizations, --sweep or a restart):
+#   --chains K              K independent chains (one thread each, seeds derived from seed) in "chain_c/",
+#                           merged in "summary.dat" with cross-chain errors and R-hat in "convergence.dat"
+# Optional sweep (not with --realizations or a restart), each point in "point_k/", all in "sweep.dat":
+#   --sweep h=0.5:1.5:11    11 values of h (or j, beta, l), or a list "h=0.9,1,1.1"
+#                           each point warm-starts from the operator string of the previous one
+# Creating "$path/STOP" also stops the run after the current bin, with a checkpoint
+# If the output directory already holds results:
+#   --on-existing refuse    stop with an error (default)
+#   --on-existing overwrite remove the results first
+#   --on-existing append    append bins of a new seed, if "run.json" has the same parameters
+./target/release/bell_qmc_tfim_1d $l $beta $J $h $num_thm $num_stat $num_bins $path $seed --bc $bc --mode $mode --lattice $lattice --ly $ly
\ No newline at end of file
@@ -1,65 +1,64 @@
-use std::time::Instant;
-use std::fs::{File, OpenOptions};
+// ------------------------------------------------------------------
+//  Helpers of the runs; the constants and the macros on the qudits
+//  and the legs are private to the crate
+// ------------------------------------------------------------------
+use std::time::{Duration, Instant};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use crate::error::{self, Error};
 
-pub const NULL_OP: i32 = -1;
-pub const NULL_QUDIT: u8 = 7;
+pub(crate) const NULL_OP: i32 = -1;
+pub(crate) const NULL_QUDIT: u8 = 7;
 
-pub const FLIPPED: i32 = -2;
-pub const NOT_FLIPPED: i32 = -1;
+pub(crate) const FLIPPED: i32 = -2;
+pub(crate) const NOT_FLIPPED: i32 = -1;
 
-pub const EMPTY: i32 = -1;
+pub(crate) const EMPTY: i32 = -1;
 
-pub const FREE_SPIN: i32 = -1;
+pub(crate) const FREE_SPIN: i32 = -1;
 
-#[macro_export]
 macro_rules! get_rx {
     ($qudit: expr) => {
         $qudit & 1
     };
 }
+pub(crate) use get_rx;
 
-#[macro_export]
 macro_rules! get_rz {
     ($qudit: expr) => {
         ($qudit >> 1) & 1
     };
 }
+pub(crate) use get_rz;
 
-#[macro_export]
 macro_rules! go_through {
     ($leg: expr) => {
         $leg ^ 0b10
     };
 }
+pub(crate) use go_through;
 
-#[macro_export]
-macro_rules! move_to_neighbor {
-    ($leg: expr) => {
-        $leg ^ 0b01
-    };
-}
-
-#[macro_export]
 macro_rules! flip_rz {
     ($qudit: expr) => {
-        $qudit ^ 0b10
+        $qudit ^= 0b10
     };
 }
+pub(crate) use flip_rz;
 
-#[macro_export]
 macro_rules! flip_rx {
     ($qudit: expr) => {
-        $qudit ^ 0b01
+        $qudit ^= 0b01
     };
 }
+pub(crate) use flip_rx;
 
 
-#[macro_export]
 macro_rules! flip_operator {
     ($op: expr) => {
-        $op ^ 1
+        $op ^= 1
     };
 }
+pub(crate) use flip_operator;
 
 pub fn report_time(start_time: Instant) {
     let elapsed = start_time.elapsed().as_secs() as u32;
@@ -70,16 +69,79 @@ pub fn report_time(start_time: Instant) {
 }
 
 pub fn print_horizontal_line(len: usize, marker: &str) {
-    for _ in 0..len { print!("{}", marker); } print!("\n");
+    for _ in 0..len { print!("{}", marker); } println!();
 }
 
-#[macro_export]
-macro_rules! dual_go_through {
-    ($leg: expr) => {
-        $leg ^ 0b01
+// ------------------------------------------------------------------
+//  A ".dat" file opened for appending, keeping its path for the errors
+// ------------------------------------------------------------------
+pub struct DatFile {
+    pub path: String,
+    file: File,
+}
+
+impl DatFile {
+    pub fn append(path: String) -> error::Result<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(&path).map_err(Error::io(&path))?;
+        Ok(Self { path, file })
+    }
+
+    pub fn write_str(&mut self, content: &str) -> error::Result<()> {
+        self.file.write_all(content.as_bytes()).map_err(Error::io(&self.path))
+    }
+}
+
+pub fn write_file(path: String, content: impl AsRef<[u8]>) -> error::Result<()> {
+    fs::write(&path, content).map_err(Error::io(&path))
+}
+
+// ------------------------------------------------------------------
+//  A duration in seconds, or with a unit "s", "m" or "h" (e.g. "90m")
+// ------------------------------------------------------------------
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&s[..i], c),
+        _ => (s, 's'),
     };
+    let scale = match unit {
+        's' => 1.0,
+        'm' => 60.0,
+        'h' => 3600.0,
+        _ => return Err(format!("invalid duration \"{}\" (unit \"s\", \"m\" or \"h\")", s)),
+    };
+    match number.parse::<f64>() {
+        Ok(x) if x >= 0.0 && x.is_finite() => Ok(Duration::from_secs_f64(x * scale)),
+        _ => Err(format!("invalid duration \"{}\"", s)),
+    }
 }
 
-pub fn create_new_file(file_path: String) -> File {
-    OpenOptions::new().write(true).append(true).create(true).open(file_path).unwrap()
-}
\ No newline at end of file
+// a row of a ".dat" file
+pub fn format_row(values: &[f64]) -> String {
+    values.iter().map(|x| format!("{:<16.10}", x)).collect::<Vec<_>>().join("\t")
+}
+
+// ------------------------------------------------------------------
+//  Mean and standard error of independent samples
+//  (the error is NaN if there are less than two samples)
+// ------------------------------------------------------------------
+pub fn mean_and_error(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    if samples.len() < 2 {
+        return (mean, f64::NAN);
+    }
+    let var = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+    (mean, (var / n).sqrt())
+}
+
+// ------------------------------------------------------------------
+//  Derive the seed of the "index"-th sub-run from a master seed
+//  (a SplitMix64 step, so that nearby indices give unrelated seeds)
+// ------------------------------------------------------------------
+pub fn derive_seed(seed: u32, index: u32) -> u32 {
+    let mut z: u64 = (((seed as u64) << 32) | index as u64).wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (z ^ (z >> 31)) as u32
+}
@@ -0,0 +1,361 @@
+/*********************************************************************************
+    Versioned binary checkpoints of a run
+    -------------------------------------------------------------------
+    Layout (little endian):
+        magic "BELLQMC\0", version (u32)
+        model       parameters, measurements, couplings, n, m, operator string, qudits,
+                    MT19937 state and accumulators (see tfim/checkpoint.rs)
+        progress    schedule, numbers of thermalization steps and finished
+                    bins, the records of the bins, the bins of the analysis,
+                    the blocking levels of the time series (see stats.rs;
+                    one per region, further correlator and Pauli string
+                    since version 6),
+                    and the lengths of the appended ".dat" files
+        checksum    FNV-1a (u64) of everything above
+    A checkpoint is written after the thermalization and after every bin
+    (and when a run stops during the thermalization), to a temporary file
+    renamed over the previous one. Resuming from it truncates the ".dat"
+    files to the recorded lengths, so that a restarted run is bit-identical
+    to an uninterrupted one.
+*********************************************************************************/
+use std::fs;
+use crate::driver::{BinRecord, Schedule};
+use crate::error::{self, Error};
+use crate::stats::{BlockLevel, Observable, Statistics, TimeSeries};
+use crate::tfim::TFIModel;
+
+const MAGIC: &[u8; 8] = b"BELLQMC\0";
+pub const VERSION: u32 = 6;
+
+// ----------------------------------------------------------------
+//  Serialization of plain values
+// ----------------------------------------------------------------
+#[derive(Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn u8(&mut self, x: u8) {
+        self.buf.push(x);
+    }
+
+    pub fn u32(&mut self, x: u32) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, x: u64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub fn usize(&mut self, x: usize) {
+        self.u64(x as u64);
+    }
+
+    pub fn f64(&mut self, x: f64) {
+        self.u64(x.to_bits());
+    }
+
+    pub fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    pub fn u8s(&mut self, xs: &[u8]) {
+        self.usize(xs.len());
+        self.buf.extend_from_slice(xs);
+    }
+
+    pub fn u32s(&mut self, xs: &[u32]) {
+        self.usize(xs.len());
+        xs.iter().for_each(|&x| self.u32(x));
+    }
+
+    pub fn i32s(&mut self, xs: &[i32]) {
+        self.usize(xs.len());
+        xs.iter().for_each(|&x| self.u32(x as u32));
+    }
+
+    pub fn f64s(&mut self, xs: &[f64]) {
+        self.usize(xs.len());
+        xs.iter().for_each(|&x| self.f64(x));
+    }
+}
+
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() - self.pos < len {
+            return Err("truncated checkpoint".to_string());
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn usize(&mut self) -> Result<usize, String> {
+        Ok(self.u64()? as usize)
+    }
+
+    pub fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    // a length, checked against the remaining bytes before allocating
+    fn len(&mut self, item_size: usize) -> Result<usize, String> {
+        let len = self.usize()?;
+        if len.saturating_mul(item_size) > self.buf.len() - self.pos {
+            return Err("truncated checkpoint".to_string());
+        }
+        Ok(len)
+    }
+
+    pub fn str(&mut self) -> Result<String, String> {
+        let len = self.len(1)?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "invalid string in checkpoint".to_string())
+    }
+
+    pub fn u8s(&mut self) -> Result<Vec<u8>, String> {
+        let len = self.len(1)?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    pub fn u32s(&mut self) -> Result<Vec<u32>, String> {
+        let len = self.len(4)?;
+        (0..len).map(|_| self.u32()).collect()
+    }
+
+    pub fn i32s(&mut self) -> Result<Vec<i32>, String> {
+        let len = self.len(4)?;
+        (0..len).map(|_| self.u32().map(|x| x as i32)).collect()
+    }
+
+    pub fn f64s(&mut self) -> Result<Vec<f64>, String> {
+        let len = self.len(8)?;
+        (0..len).map(|_| self.f64()).collect()
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+// ----------------------------------------------------------------
+//  Progress of the driver
+// ----------------------------------------------------------------
+pub struct Progress {
+    pub num_thm_done: usize,            // thermalization steps, up to num_thm
+    pub num_bins_done: usize,
+    pub records: Vec<BinRecord>,
+    pub stats: Statistics,              // bins of the binning analysis
+    pub series: Vec<TimeSeries>,        // time series of the autocorrelation analysis
+    pub file_lengths: Vec<(String, u64)>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self {
+            num_thm_done: 0,
+            num_bins_done: 0,
+            records: Vec::new(),
+            stats: Statistics::new(),
+            series: Vec::new(),
+            file_lengths: Vec::new(),
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write_record(w: &mut Writer, rec: &BinRecord) {
+    w.f64(rec.expansion_order);
+    w.f64(rec.purity);
+    w.f64(rec.partial_purity);
+    w.f64(rec.complement_purity);
+    w.f64s(&rec.region_purity);
+    w.f64s(&rec.profile_purity);
+    w.f64s(&rec.zz_corr_2);
+    w.f64s(&rec.xx_corr_2);
+    w.usize(rec.pauli_corr_2.len());
+    rec.pauli_corr_2.iter().for_each(|corr| w.f64s(corr));
+    w.f64s(&rec.pauli_string_2);
+    w.f64s(&rec.zz_corr_matrix);
+    w.f64s(&rec.xx_corr_matrix);
+}
+
+fn read_record(r: &mut Reader) -> Result<BinRecord, String> {
+    Ok(BinRecord {
+        expansion_order: r.f64()?,
+        purity: r.f64()?,
+        partial_purity: r.f64()?,
+        complement_purity: r.f64()?,
+        region_purity: r.f64s()?,
+        profile_purity: r.f64s()?,
+        zz_corr_2: r.f64s()?,
+        xx_corr_2: r.f64s()?,
+        pauli_corr_2: {
+            let len = r.len(8)?;
+            (0..len).map(|_| r.f64s()).collect::<Result<_, _>>()?
+        },
+        pauli_string_2: r.f64s()?,
+        zz_corr_matrix: r.f64s()?,
+        xx_corr_matrix: r.f64s()?,
+    })
+}
+
+fn write_statistics(w: &mut Writer, stats: &Statistics) {
+    w.usize(stats.observables.len());
+    for obs in stats.observables.iter() {
+        w.str(&obs.name);
+        w.f64s(&obs.bins);
+    }
+}
+
+fn read_statistics(r: &mut Reader) -> Result<Statistics, String> {
+    let len = r.len(16)?;
+    let mut stats = Statistics::new();
+    for _ in 0..len {
+        stats.insert(Observable { name: r.str()?, bins: r.f64s()? });
+    }
+    Ok(stats)
+}
+
+fn write_series(w: &mut Writer, series: &[TimeSeries]) {
+    w.usize(series.len());
+    for s in series.iter() {
+        w.str(&s.name);
+        w.usize(s.levels.len());
+        for level in s.levels.iter() {
+            w.usize(level.num_blocks);
+            w.f64(level.mean);
+            w.f64(level.m2);
+            w.u8(level.pending.is_some() as u8);
+            w.f64(level.pending.unwrap_or(0.0));
+        }
+    }
+}
+
+fn read_series(r: &mut Reader) -> Result<Vec<TimeSeries>, String> {
+    let len = r.len(16)?;
+    (0..len)
+        .map(|_| {
+            let mut s = TimeSeries::new(&r.str()?);
+            let num_levels = r.len(33)?;
+            for _ in 0..num_levels {
+                let (num_blocks, mean, m2) = (r.usize()?, r.f64()?, r.f64()?);
+                let (has_pending, pending) = (r.u8()?, r.f64()?);
+                s.levels.push(BlockLevel { num_blocks, mean, m2, pending: (has_pending != 0).then_some(pending) });
+            }
+            Ok(s)
+        })
+        .collect()
+}
+
+// ==========================================================================
+//  Save the model and the progress to "path"
+// ==========================================================================
+pub fn save(path: &str, model: &TFIModel, schedule: Schedule, progress: &Progress) -> error::Result<()> {
+    let mut w = Writer::new();
+    w.buf.extend_from_slice(MAGIC);
+    w.u32(VERSION);
+
+    model.write_checkpoint(&mut w);
+
+    w.usize(schedule.num_thm);
+    w.usize(schedule.num_stat);
+    w.usize(progress.num_thm_done);
+    w.usize(progress.num_bins_done);
+    w.usize(progress.records.len());
+    progress.records.iter().for_each(|rec| write_record(&mut w, rec));
+    write_statistics(&mut w, &progress.stats);
+    write_series(&mut w, &progress.series);
+    w.usize(progress.file_lengths.len());
+    for (file_path, len) in progress.file_lengths.iter() {
+        w.str(file_path);
+        w.u64(*len);
+    }
+
+    let checksum = fnv1a(&w.buf);
+    w.u64(checksum);
+
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(&tmp_path, &w.buf).map_err(Error::io(&tmp_path))?;
+    fs::rename(&tmp_path, path).map_err(Error::io(path))
+}
+
+// ==========================================================================
+//  Restore the model (initialized with the same parameters) from "path",
+//  and return the progress of the run
+// ==========================================================================
+pub fn load(path: &str, model: &mut TFIModel, schedule: Schedule) -> error::Result<Progress> {
+    let bytes = fs::read(path).map_err(Error::io(path))?;
+    read(&bytes, path, model, schedule).map_err(Error::Checkpoint)
+}
+
+fn read(bytes: &[u8], path: &str, model: &mut TFIModel, schedule: Schedule) -> Result<Progress, String> {
+    if bytes.len() < MAGIC.len() + 12 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(format!("\"{}\" is not a checkpoint", path));
+    }
+    let (payload, checksum) = bytes.split_at(bytes.len() - 8);
+    if fnv1a(payload) != u64::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(format!("checkpoint \"{}\" is corrupted (checksum mismatch)", path));
+    }
+
+    let mut r = Reader::new(payload);
+    r.take(MAGIC.len())?;
+    let version = r.u32()?;
+    if version != VERSION {
+        return Err(format!("checkpoint \"{}\" has version {}, expected {}", path, version, VERSION));
+    }
+
+    model.read_checkpoint(&mut r).map_err(|e| format!("\"{}\": {}", path, e))?;
+
+    let (num_thm, num_stat) = (r.usize()?, r.usize()?);
+    if (num_thm, num_stat) != (schedule.num_thm, schedule.num_stat) {
+        return Err(format!(
+            "checkpoint \"{}\" was written with num_thm = {}, num_stat = {} (now {}, {})",
+            path, num_thm, num_stat, schedule.num_thm, schedule.num_stat
+        ));
+    }
+
+    let num_thm_done = r.usize()?;
+    let num_bins_done = r.usize()?;
+    let num_records = r.len(8)?;
+    let records: Vec<BinRecord> = (0..num_records).map(|_| read_record(&mut r)).collect::<Result<_, _>>()?;
+    let stats = read_statistics(&mut r)?;
+    let series = read_series(&mut r)?;
+    let num_files = r.len(16)?;
+    let file_lengths: Vec<(String, u64)> = (0..num_files)
+        .map(|_| Ok((r.str()?, r.u64()?)))
+        .collect::<Result<_, String>>()?;
+
+    Ok(Progress { num_thm_done, num_bins_done, records, stats, series, file_lengths })
+}
@@ -0,0 +1,639 @@
+/*********************************************************************************
+    Parameters of a run, from a config file and the command line
+    -------------------------------------------------------------------
+    A config file ("--config run.conf") has one "key = value" per line,
+    "#" starting a comment. Optional "[section]" headers group the keys
+    and are checked against them:
+        [model]         l, beta, j, h, mode, seed
+        [lattice]       lattice, ly, bc
+        [schedule]      num-thm, num-stat, num-bins, checkpoint, max-runtime,
+                        chains, sweep, num-thm-warm
+                        ("num-thm-warm" defaults to "num-thm": the warm
+                        starts of a sweep save thermalization only when
+                        it is set lower)
+        [couplings]     couplings, disorder, disorder-seed, realizations
+        [observables]   regions, regions-file, ref-site, corr, corr-matrix,
+                        pauli-strings, exact, free-fermion
+        [output]        output, on-existing
+    Values may be quoted ("..." or '...', which may hold a "#"), and "_"
+    in a key is read as "-".
+    -------------------------------------------------------------------
+    On the command line, the nine positional arguments
+        l beta J h num_thm num_stat num_bins output seed
+    are optional and followed by "--key value" pairs; both override the
+    config file.
+    -------------------------------------------------------------------
+    "SimulationConfig" holds the typed and validated parameters.
+*********************************************************************************/
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::aux;
+use crate::driver::{DisorderAverage, Schedule};
+use crate::ed;
+use crate::error::{self, Error};
+pub use crate::output::OnExisting;
+use crate::sweep::{Sweep, SweepParameter};
+use crate::tfim::{Boundary, Couplings, Disorder, Lattice, LatticeKind, PauliPair, PauliString, Region, SimulationMode, TFIModel};
+
+/// The parameters as (key, section, description), in the order of the usage.
+pub const KEYS: &[(&str, &str, &str)] = &[
+    ("l", "model", "number of sites of a chain (the length l for the other lattices)"),
+    ("beta", "model", "inverse temperature of each copy"),
+    ("j", "model", "coupling strength of the Ising interaction"),
+    ("h", "model", "strength of the magnetic fields"),
+    ("mode", "model", "\"gs\" (ground state projection) or \"ft\" (finite temperature)"),
+    ("seed", "model", "seed of the PRNG"),
+    ("lattice", "lattice", "\"chain\", \"ladder\", \"square\" or \"triangular\""),
+    ("ly", "lattice", "width in the second direction"),
+    ("bc", "lattice", "boundary conditions, \"obc\" or \"pbc\""),
+    ("num-thm", "schedule", "number of MC steps for thermalization"),
+    ("num-stat", "schedule", "number of MC steps in each bin"),
+    ("num-bins", "schedule", "number of bins"),
+    ("checkpoint", "schedule", "checkpoint file, resumed from if it exists"),
+    ("max-runtime", "schedule", "stop with a checkpoint before exceeding it"),
+    ("chains", "schedule", "number of independent Markov chains run in parallel"),
+    ("sweep", "schedule", "sweep of h, j, beta or l with warm starts, e.g. \"h=0.5:1.5:11\" or \"h=0.9,1,1.1\""),
+    ("num-thm-warm", "schedule", "number of MC steps for thermalization of the warm-started points of a sweep (default num-thm, i.e. no saving unless set lower)"),
+    ("couplings", "couplings", "file of site- and bond-dependent couplings"),
+    ("disorder", "couplings", "random couplings, \"box\" or \"log:W\""),
+    ("disorder-seed", "couplings", "seed of the random couplings"),
+    ("realizations", "couplings", "number of disorder realizations to average over"),
+    ("regions", "observables", "regions of the Renyi-2 entropies, e.g. \"A=0..4;B=0,2\""),
+    ("regions-file", "observables", "file of regions, one per line"),
+    ("ref-site", "observables", "reference site of the correlators"),
+    ("corr", "observables", "further correlators, e.g. \"yy,xy\""),
+    ("corr-matrix", "observables", "all-pairs correlation matrices (true or false)"),
+    ("pauli-strings", "observables", "file of Pauli strings, one per line"),
+    ("exact", "observables", "compare with exact diagonalization (true or false), small lattices only"),
+    ("free-fermion", "observables", "exact ground state of the chain from free fermions (true or false)"),
+    ("output", "output", "output directory"),
+    ("on-existing", "output", "with results in the output directory: \"refuse\", \"overwrite\" or \"append\""),
+];
+
+// the keys of the positional arguments, in order
+const POSITIONAL: [&str; 9] = ["l", "beta", "j", "h", "num-thm", "num-stat", "num-bins", "output", "seed"];
+
+#[derive(Clone, Debug)]
+struct Entry {
+    value: String,
+    origin: String,     // e.g. "run.conf:3" or "option \"--beta\""
+}
+
+/// The raw values of the parameters, each with its origin for the errors
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    entries: HashMap<String, Entry>,
+}
+
+// "num_thm" and "num-thm" are the same key
+fn normalize(key: &str) -> String {
+    key.trim().to_ascii_lowercase().replace('_', "-")
+}
+
+fn section_of(key: &str) -> Option<&'static str> {
+    KEYS.iter().find(|(k, _, _)| *k == key).map(|(_, section, _)| *section)
+}
+
+// the line up to its "#" comment, a "#" inside quotes being part of the value
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') => return &line[..i],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+impl Config {
+    /// An empty config.
+    pub fn new() -> Self {
+        Self { entries: HashMap::new() }
+    }
+
+    fn insert(&mut self, key: &str, value: &str, origin: String) -> Result<(), String> {
+        let key = normalize(key);
+        if section_of(&key).is_none() {
+            return Err(format!("{}: unknown parameter \"{}\"", origin, key));
+        }
+        self.entries.insert(key, Entry { value: value.to_string(), origin });
+        Ok(())
+    }
+
+    /// Parse the "key = value" lines of a config file
+    pub fn parse(content: &str, file_path: &str) -> Result<Self, String> {
+        let mut config = Self::new();
+        let mut section: Option<String> = None;
+        for (i, line) in content.lines().enumerate() {
+            let origin = format!("{}:{}", file_path, i + 1);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                let name = name.trim().to_ascii_lowercase();
+                if !KEYS.iter().any(|(_, s, _)| *s == name) {
+                    return Err(format!("{}: unknown section \"[{}]\"", origin, name));
+                }
+                section = Some(name);
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("{}: expected \"key = value\", found \"{}\"", origin, line))?;
+            let value = value.trim();
+            let value = match (value.chars().next(), value.chars().last()) {
+                (Some(q @ ('"' | '\'')), Some(r)) if value.len() >= 2 && q == r => &value[1..value.len() - 1],
+                (Some(q @ ('"' | '\'')), _) => return Err(format!("{}: unterminated {} in \"{}\"", origin, q, line)),
+                _ => value,
+            };
+            if let (Some(section), Some(expected)) = (&section, section_of(&normalize(key)))
+                && section != expected
+            {
+                return Err(format!(
+                    "{}: \"{}\" belongs to section \"[{}]\", not \"[{}]\"", origin, normalize(key), expected, section
+                ));
+            }
+            config.insert(key, value, origin)?;
+        }
+        Ok(config)
+    }
+
+    /// Parse the config file at `file_path`.
+    pub fn from_file(file_path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("cannot read config file \"{}\": {}", file_path, e))?;
+        Self::parse(&content, file_path)
+    }
+
+    /// The command line (without the program name): the config file
+    /// of "--config", overridden by the positional arguments and the
+    /// "--key value" pairs
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let num_positional = args.iter().take_while(|arg| !arg.starts_with("--")).count();
+        if num_positional != 0 && num_positional != POSITIONAL.len() {
+            return Err(format!(
+                "expected the {} positional arguments \"{}\" or none, found {}",
+                POSITIONAL.len(), POSITIONAL.join(" "), num_positional
+            ));
+        }
+
+        let mut cli = Self::new();
+        let mut config_path: Option<&String> = None;
+        for (key, value) in POSITIONAL.iter().zip(&args[..num_positional]) {
+            cli.insert(key, value, format!("positional argument \"{}\"", key))?;
+        }
+        let mut iter = args[num_positional..].iter();
+        while let Some(arg) = iter.next() {
+            let key = arg.strip_prefix("--").ok_or_else(|| format!("unexpected argument \"{}\"", arg))?;
+            let value = iter.next().ok_or_else(|| format!("missing value for option \"--{}\"", key))?;
+            if normalize(key) == "config" {
+                config_path = Some(value);
+            } else {
+                cli.insert(key, value, format!("option \"--{}\"", key))?;
+            }
+        }
+
+        let mut config = match config_path {
+            Some(file_path) => Self::from_file(file_path)?,
+            None => Self::new(),
+        };
+        config.merge(cli);
+        Ok(config)
+    }
+
+    /// The values of `other` override those of `self`.
+    pub fn merge(&mut self, other: Self) {
+        self.entries.extend(other.entries);
+    }
+
+    // typed access, the errors naming the origin of the value
+
+    /// Whether `key` was given.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// The raw value of `key`.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(|entry| entry.value.as_str())
+    }
+
+    /// The value of `key` parsed as a `T`, if given.
+    pub fn get<T>(&self, key: &str) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.entries.get(key) {
+            Some(entry) => entry.value.parse::<T>().map(Some).map_err(|e| {
+                format!("{}: invalid value \"{}\" for \"{}\": {}", entry.origin, entry.value, key, e)
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// The value of `key` parsed as a `T`, or `default`.
+    pub fn get_or<T>(&self, key: &str, default: T) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        Ok(self.get(key)?.unwrap_or(default))
+    }
+
+    /// The value of `key` parsed as a `T`, an error if it is missing.
+    pub fn require<T>(&self, key: &str) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get(key)?.ok_or_else(|| {
+            let (_, section, description) = KEYS.iter().find(|(k, _, _)| *k == key).unwrap();
+            format!("missing parameter \"{}\" ([{}], {})", key, section, description)
+        })
+    }
+}
+
+/// The typed parameters of a run, checked by "new" and "validate"
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    /// The lattice of the model.
+    pub lattice: Lattice,
+    /// The inverse temperature of each copy.
+    pub beta: f64,
+    /// The coupling strength of the Ising interaction.
+    pub j: f64,
+    /// The strength of the magnetic fields.
+    pub h: f64,
+    /// Ground-state projection or finite temperature.
+    pub mode: SimulationMode,
+    /// The seed of the PRNG.
+    pub seed: u32,
+    /// The numbers of MC steps and bins.
+    pub schedule: Schedule,
+    /// The output directory.
+    pub output: String,
+    /// What to do with the results already there.
+    pub on_existing: OnExisting,
+    /// The checkpoint file, resumed from if it exists.
+    pub checkpoint: Option<String>,
+    /// Stop with a checkpoint before exceeding it.
+    pub max_runtime: Option<Duration>,
+    /// The number of independent Markov chains, 1 for a single one.
+    pub num_chains: usize,
+    /// The sweep of a parameter with warm starts.
+    pub sweep: Option<Sweep>,
+    /// The thermalization of the warm starts of the sweep.
+    pub num_thm_warm: Option<usize>,
+    /// The file of site- and bond-dependent couplings.
+    pub couplings_file: Option<String>,
+    /// The distribution of the random couplings.
+    pub disorder: Option<Disorder>,
+    /// The seed of the random couplings.
+    pub disorder_seed: u32,
+    /// The number of disorder realizations, 0 for a single run.
+    pub num_realizations: usize,
+    /// The regions of the Renyi-2 entropies.
+    pub regions: Vec<Region>,
+    /// The reference site of the correlators.
+    pub ref_site: usize,
+    /// Whether the all-pairs correlation matrices are measured.
+    pub corr_matrix: bool,
+    /// The further correlators, without "zz" and "xx", always measured.
+    pub pauli_pairs: Vec<PauliPair>,
+    /// The Pauli strings P whose tr(rho P)^2 are estimated.
+    pub pauli_strings: Vec<PauliString>,
+    /// Whether to compare with exact diagonalization.
+    pub exact: bool,
+    /// Whether to compute the free-fermion ground state of the chain.
+    pub free_fermion: bool,
+}
+
+impl SimulationConfig {
+    /// The typed parameters of `config`, validated.
+    pub fn new(config: &Config) -> error::Result<Self> {
+        let l: usize = config.require("l").map_err(Error::Config)?;
+        let kind: LatticeKind = config.get_or("lattice", LatticeKind::Chain).map_err(Error::Config)?;
+        let boundary: Boundary = config.get_or("bc", Boundary::Open).map_err(Error::Config)?;
+        let ly: usize = match config.get("ly").map_err(Error::Config)? {
+            Some(ly) => ly,
+            None if kind == LatticeKind::Ladder => 2,
+            None => l,
+        };
+        let lattice = Lattice::new(kind, l, ly, boundary).map_err(Error::Lattice)?;
+        let num_sites = lattice.num_sites;
+
+        // user-defined regions for the Renyi-2 entropies, from "regions" and/or "regions-file"
+        let mut regions: Vec<Region> = match config.get_str("regions") {
+            Some(specs) => Region::parse_list(specs, num_sites).map_err(Error::Config)?,
+            None => Vec::new(),
+        };
+        if let Some(file_path) = config.get_str("regions-file") {
+            regions.extend(Region::from_file(file_path, num_sites).map_err(Error::Config)?);
+            Region::check_names(&regions).map_err(Error::Config)?;
+        }
+
+        // further two-site correlators, e.g. "yy,xy,xz" ("zz" and "xx" are always measured)
+        let pauli_pairs: Vec<PauliPair> = match config.get_str("corr") {
+            Some(specs) => PauliPair::parse_list(specs)
+                .map_err(Error::Config)?
+                .into_iter()
+                .filter(|pair| !matches!(pair.to_string().as_str(), "ZZ" | "XX"))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        // Pauli strings P whose tr(rho P)^2 are estimated, one per line of "pauli-strings"
+        let pauli_strings: Vec<PauliString> = match config.get_str("pauli-strings") {
+            Some(file_path) => PauliString::from_file(file_path, num_sites).map_err(Error::Config)?,
+            None => Vec::new(),
+        };
+
+        let seed: u32 = config.require("seed").map_err(Error::Config)?;
+        // unless given, the disorder seed is derived from the seed so that the couplings
+        // and the Markov chain never share the same stream of random numbers
+        let disorder_seed: u32 = config
+            .get("disorder-seed")
+            .map_err(Error::Config)?
+            .unwrap_or_else(|| aux::derive_seed(seed, u32::MAX));
+
+        let para = Self {
+            lattice,
+            beta: config.require("beta").map_err(Error::Config)?,
+            j: config.require("j").map_err(Error::Config)?,
+            h: config.require("h").map_err(Error::Config)?,
+            mode: config.get_or("mode", SimulationMode::GroundState).map_err(Error::Config)?,
+            seed,
+            schedule: Schedule {
+                num_thm: config.require("num-thm").map_err(Error::Config)?,
+                num_stat: config.require("num-stat").map_err(Error::Config)?,
+                num_bins: config.require("num-bins").map_err(Error::Config)?,
+            },
+            output: config.require("output").map_err(Error::Config)?,
+            on_existing: config.get_or("on-existing", OnExisting::Refuse).map_err(Error::Config)?,
+            checkpoint: config.get_str("checkpoint").map(|path| path.to_string()),
+            max_runtime: config.get_str("max-runtime").map(aux::parse_duration).transpose().map_err(Error::Config)?,
+            num_chains: config.get_or("chains", 1).map_err(Error::Config)?,
+            sweep: config.get("sweep").map_err(Error::Config)?,
+            num_thm_warm: config.get("num-thm-warm").map_err(Error::Config)?,
+            couplings_file: config.get_str("couplings").map(|path| path.to_string()),
+            disorder: config.get("disorder").map_err(Error::Config)?,
+            disorder_seed,
+            num_realizations: config.get_or("realizations", 0).map_err(Error::Config)?,
+            regions,
+            ref_site: config.get_or("ref-site", 0).map_err(Error::Config)?,
+            corr_matrix: config.get_or("corr-matrix", false).map_err(Error::Config)?,
+            pauli_pairs,
+            pauli_strings,
+            exact: config.get_or("exact", false).map_err(Error::Config)?,
+            free_fermion: config.get_or("free-fermion", false).map_err(Error::Config)?,
+        };
+        para.validate()?;
+        Ok(para)
+    }
+
+    /// The typed parameters of the command line, see [`Config::from_args`].
+    pub fn from_args(args: &[String]) -> error::Result<Self> {
+        Self::new(&Config::from_args(args).map_err(Error::Config)?)
+    }
+
+    /// The ranges and combinations the parsing alone does not check
+    pub fn validate(&self) -> error::Result<()> {
+        let error = |msg: String| Err(Error::Config(msg));
+        if !(self.beta > 0.0 && self.beta.is_finite()) {
+            return error(format!("\"beta\" must be positive, got {}", self.beta));
+        }
+        if !(self.j >= 0.0 && self.h >= 0.0 && self.j.is_finite() && self.h.is_finite()) {
+            return error(format!("\"j\" and \"h\" must be non-negative, got {} and {}", self.j, self.h));
+        }
+        if self.schedule.num_stat == 0 || self.schedule.num_bins == 0 {
+            return error("\"num-stat\" and \"num-bins\" must be positive".to_string());
+        }
+        if self.output.is_empty() {
+            return error("\"output\" must not be empty".to_string());
+        }
+        if self.ref_site >= self.lattice.num_sites {
+            return error(format!("reference site {} out of range (< {})", self.ref_site, self.lattice.num_sites));
+        }
+        if self.couplings_file.is_some() && self.disorder.is_some() {
+            return error("parameters \"couplings\" and \"disorder\" are exclusive".to_string());
+        }
+        if self.num_realizations > 0 {
+            if self.disorder.is_none() {
+                return error("parameter \"realizations\" requires \"disorder\"".to_string());
+            }
+            if self.checkpoint.is_some() {
+                return error("parameter \"checkpoint\" is not supported with \"realizations\" (each realization has its own)".to_string());
+            }
+        }
+        if self.num_chains == 0 {
+            return error("\"chains\" must be positive".to_string());
+        }
+        if self.num_chains > 1 {
+            if self.num_realizations > 0 || self.sweep.is_some() || self.checkpoint.is_some() {
+                return error("parameters \"realizations\", \"sweep\" and \"checkpoint\" are not supported with \"chains\" (each chain has its own checkpoint)".to_string());
+            }
+            if self.on_existing == OnExisting::Append {
+                return error("\"on-existing = append\" is not supported with \"chains\"".to_string());
+            }
+        }
+        if self.num_thm_warm.is_some() && self.sweep.is_none() {
+            return error("parameter \"num-thm-warm\" requires \"sweep\"".to_string());
+        }
+        if let Some(sweep) = self.sweep.as_ref() {
+            if self.num_realizations > 0 || self.checkpoint.is_some() {
+                return error("parameters \"realizations\" and \"checkpoint\" are not supported with \"sweep\" (each point has its own checkpoint)".to_string());
+            }
+            if self.on_existing == OnExisting::Append {
+                return error("\"on-existing = append\" is not supported with \"sweep\"".to_string());
+            }
+            if sweep.parameter == SweepParameter::L && !(self.regions.is_empty() && self.pauli_strings.is_empty()) {
+                return error("a sweep over \"l\" does not support \"regions\" and \"pauli-strings\"".to_string());
+            }
+            for k in 0..sweep.values.len() {
+                sweep.point(self, k)?;
+            }
+        }
+        if self.exact {
+            if self.num_realizations > 0 || self.sweep.is_some() || self.num_chains > 1 {
+                return error("\"exact\" is not supported with \"realizations\", \"sweep\" and \"chains\"".to_string());
+            }
+            let max_sites = match self.mode {
+                SimulationMode::GroundState => ed::MAX_SITES_GROUND_STATE,
+                SimulationMode::FiniteTemperature => ed::MAX_SITES_THERMAL,
+            };
+            if self.lattice.num_sites > max_sites {
+                return error(format!(
+                    "\"exact\" is limited to {} sites in mode \"{}\", {} has {}", max_sites, self.mode, self.lattice, self.lattice.num_sites
+                ));
+            }
+        }
+        if self.free_fermion {
+            if self.num_realizations > 0 || self.sweep.is_some() {
+                return error("\"free-fermion\" is not supported with \"realizations\" and \"sweep\"".to_string());
+            }
+            if self.lattice.kind != LatticeKind::Chain || self.mode != SimulationMode::GroundState {
+                return error("\"free-fermion\" requires a chain in mode \"gs\"".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Uniform couplings unless a couplings file or a disorder distribution is given.
+    pub fn couplings(&self) -> error::Result<Couplings> {
+        match (&self.couplings_file, self.disorder) {
+            (Some(file_path), _) => Couplings::from_file(&self.lattice, self.j, self.h, file_path).map_err(Error::Config),
+            (None, Some(disorder)) => Ok(Couplings::random(&self.lattice, self.j, self.h, disorder, self.disorder_seed)),
+            (None, None) => Ok(Couplings::uniform(&self.lattice, self.j, self.h)),
+        }
+    }
+
+    /// An initialized model with the requested measurements.
+    pub fn build_model(&self, couplings: Couplings, seed: u32) -> error::Result<TFIModel> {
+        let mut model = TFIModel::new(self.lattice.clone(), self.beta, couplings, seed, self.mode)?;
+        model.init()?;
+        model.set_regions(self.regions.clone())?;
+        model.set_ref_site(self.ref_site)?;
+        model.set_corr_matrix(self.corr_matrix);
+        model.set_pauli_pairs(self.pauli_pairs.clone());
+        model.set_pauli_strings(self.pauli_strings.clone());
+        Ok(model)
+    }
+
+    /// The disorder average of the run, if any.
+    pub fn disorder_average(&self) -> Option<DisorderAverage> {
+        Some(DisorderAverage {
+            disorder: self.disorder?,
+            disorder_seed: self.disorder_seed,
+            num_realizations: self.num_realizations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    // a config file in the temporary directory, unique to this process
+    fn config_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("bell_qmc_config_{}_{}.conf", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    const BASE: &str = "--l 8 --beta 2 --j 1 --h 0.5 --num-thm 10 --num-stat 10 --num-bins 2 --output out --seed 7";
+
+    #[test]
+    fn parses_sections_comments_and_quotes() {
+        let content = "# a run\n[model]\nl = 8   # sites\nbeta = 2.5\n[schedule]\nnum_thm = 100\n\n[output]\noutput = \"data/run 1\"\n";
+        let config = Config::parse(content, "run.conf").unwrap();
+        assert_eq!(config.get::<usize>("l").unwrap(), Some(8));
+        assert_eq!(config.get::<f64>("beta").unwrap(), Some(2.5));
+        assert_eq!(config.get::<usize>("num-thm").unwrap(), Some(100));
+        assert_eq!(config.get_str("output"), Some("data/run 1"));
+        assert_eq!(config.get_or("h", 1.0).unwrap(), 1.0);
+
+        // a "#" inside quotes is no comment
+        let config = Config::parse("output = \"runs#1\"  # the #1\nregions = 'A=0#B'\n", "run.conf").unwrap();
+        assert_eq!(config.get_str("output"), Some("runs#1"));
+        assert_eq!(config.get_str("regions"), Some("A=0#B"));
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        for (content, expected) in [
+            ("l 8\n", "run.conf:1: expected \"key = value\""),
+            ("[model]\nsize = 8\n", "run.conf:2: unknown parameter \"size\""),
+            ("[physics]\n", "run.conf:1: unknown section \"[physics]\""),
+            ("[lattice]\nbeta = 1\n", "run.conf:2: \"beta\" belongs to section \"[model]\", not \"[lattice]\""),
+            ("output = \"runs#1\n", "run.conf:1: unterminated \" in \"output = \"runs#1\""),
+        ] {
+            let error = Config::parse(content, "run.conf").unwrap_err();
+            assert!(error.starts_with(expected), "{:?}: {}", content, error);
+        }
+    }
+
+    #[test]
+    fn typed_access_names_the_origin() {
+        let config = Config::parse("l = eight\n", "run.conf").unwrap();
+        let error = config.get::<usize>("l").unwrap_err();
+        assert!(error.starts_with("run.conf:1: invalid value \"eight\" for \"l\""), "{}", error);
+        let error = config.require::<f64>("beta").unwrap_err();
+        assert!(error.starts_with("missing parameter \"beta\" ([model]"), "{}", error);
+    }
+
+    #[test]
+    fn command_line_overrides_the_file() {
+        let path = config_file("override", "l = 4\nbeta = 1\nh = 0.3\n");
+        let config = Config::from_args(&args(&format!("--config {} --beta 3", path))).unwrap();
+        assert_eq!(config.get::<usize>("l").unwrap(), Some(4));
+        assert_eq!(config.get::<f64>("beta").unwrap(), Some(3.0));
+
+        // the positional arguments override the file as well, the options override both
+        let positional = format!("16 2 1 0.5 10 20 3 out 7 --config {} --h 0.9", path);
+        let config = Config::from_args(&args(&positional)).unwrap();
+        assert_eq!(config.get::<usize>("l").unwrap(), Some(16));
+        assert_eq!(config.get::<f64>("beta").unwrap(), Some(2.0));
+        assert_eq!(config.get::<f64>("h").unwrap(), Some(0.9));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_command_lines() {
+        for (line, expected) in [
+            ("8 2 1", "expected the 9 positional arguments"),
+            ("--l", "missing value for option \"--l\""),
+            ("--l 8 beta", "unexpected argument \"beta\""),
+            ("--size 8", "option \"--size\": unknown parameter \"size\""),
+            ("--config /nonexistent/run.conf", "cannot read config file \"/nonexistent/run.conf\""),
+        ] {
+            let error = Config::from_args(&args(line)).unwrap_err();
+            assert!(error.starts_with(expected), "{:?}: {}", line, error);
+        }
+    }
+
+    #[test]
+    fn builds_a_valid_simulation_config() {
+        let para = SimulationConfig::from_args(&args(&format!("{} --regions A=0..4;B=0,2 --corr yy,zz", BASE))).unwrap();
+        assert_eq!(para.lattice.num_sites, 8);
+        assert_eq!((para.beta, para.j, para.h, para.seed), (2.0, 1.0, 0.5, 7));
+        assert_eq!(para.regions.len(), 2);
+        assert_eq!(para.pauli_pairs.len(), 1);
+        assert_eq!(para.on_existing, OnExisting::Refuse);
+        assert_eq!(para.num_chains, 1);
+    }
+
+    #[test]
+    fn rejects_invalid_simulation_configs() {
+        for (extra, expected) in [
+            ("--beta 0", "\"beta\" must be positive"),
+            ("--h -1", "\"j\" and \"h\" must be non-negative"),
+            ("--num-bins 0", "\"num-stat\" and \"num-bins\" must be positive"),
+            ("--ref-site 8", "reference site 8 out of range"),
+            ("--realizations 4", "parameter \"realizations\" requires \"disorder\""),
+            ("--chains 0", "\"chains\" must be positive"),
+            ("--num-thm-warm 5", "parameter \"num-thm-warm\" requires \"sweep\""),
+            ("--free-fermion true --mode ft", "\"free-fermion\" requires a chain in mode \"gs\""),
+            ("--exact true --l 20", "\"exact\" is limited to 14 sites"),
+            ("--regions A=0..9", "region \"A\": site 8 out of range"),
+            ("--regions A=0;A=1", "region \"A\" is defined twice"),
+            ("--corr zq", "invalid correlator \"zq\""),
+            ("--on-existing keep", "option \"--on-existing\": invalid value \"keep\""),
+        ] {
+            let error = SimulationConfig::from_args(&args(&format!("{} {}", BASE, extra))).unwrap_err().to_string();
+            assert!(error.contains(expected), "{:?}: {}", extra, error);
+        }
+    }
+}
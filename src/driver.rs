@@ -0,0 +1,833 @@
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::aux::{self, DatFile};
+use crate::checkpoint::{self, Progress};
+use crate::config::SimulationConfig;
+use crate::error::{Error, Result};
+use crate::stats::{self, Statistics, TimeSeries};
+use crate::tfim::{TFIModel, Couplings, Disorder, SimulationMode};
+
+/// Bin averages of the measured observables
+pub struct BinRecord {
+    /// The average number n of non-null operators.
+    pub expansion_order: f64,
+    /// tr(rho^2) of the whole system.
+    pub purity: f64,
+    /// tr(rho_A^2) of the half system A.
+    pub partial_purity: f64,
+    /// tr(rho_B^2) of the other half, only measured at finite temperature.
+    pub complement_purity: f64,
+    /// The purities of the user-defined regions.
+    pub region_purity: Vec<f64>,
+    /// The purities of the cuts [0, l), l = 1, ..., num_sites - 1.
+    pub profile_purity: Vec<f64>,
+    /// tr(rho Z_r Z_j)^2 for every site j.
+    pub zz_corr_2: Vec<f64>,
+    /// tr(rho X_r X_j)^2 for every site j.
+    pub xx_corr_2: Vec<f64>,
+    /// The further correlators "PQ", in the order of the model.
+    pub pauli_corr_2: Vec<Vec<f64>>,
+    /// tr(rho P)^2 of the Pauli strings.
+    pub pauli_string_2: Vec<f64>,
+    /// tr(rho Z_i Z_j)^2 of all pairs (empty unless enabled).
+    pub zz_corr_matrix: Vec<f64>,
+    /// tr(rho X_i X_j)^2 of all pairs (empty unless enabled).
+    pub xx_corr_matrix: Vec<f64>,
+}
+
+// the thermalization polls "max_runtime" and the "STOP" file every THM_POLL steps
+const THM_POLL: usize = 100;
+
+// the per-step time series of the autocorrelation analysis, in this order,
+// followed by those of the model's further observables (see "series_names");
+// the correlators get a tau_int for their site average only, not for the
+// single sites j, and the profile of the cuts gets none
+const SERIES: [&str; 5] = ["purity", "partial_purity", "expansion_order", "zz_corr_2_avg", "xx_corr_2_avg"];
+
+/// The bins of a run, and how far it got
+pub struct Outcome {
+    /// The bins done, all of them unless the run stopped early.
+    pub records: Vec<BinRecord>,
+    /// The thermalization steps done.
+    pub num_thm_done: usize,
+}
+
+/// What the runs of a command line have done, summed over the points of a
+/// sweep, the realizations of a disorder average or parallel chains
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunSummary {
+    /// Whether a run stopped early, or runs were left out.
+    pub stopped: bool,
+    /// The thermalization steps done.
+    pub num_thm_done: usize,
+    /// The bins done.
+    pub num_bins_done: usize,
+    /// The largest cut-off m at the end of the runs.
+    pub final_m: Option<usize>,
+    /// The sum over the bins done of the average expansion order n.
+    pub sum_n: f64,
+}
+
+impl RunSummary {
+    /// Add a run of `model` that was to measure `num_bins` bins.
+    pub fn add(&mut self, model: &TFIModel, outcome: &Outcome, num_bins: usize) {
+        self.stopped |= outcome.records.len() < num_bins;
+        self.num_thm_done += outcome.num_thm_done;
+        self.num_bins_done += outcome.records.len();
+        self.final_m = self.final_m.max(Some(model.m()));
+        self.sum_n += outcome.records.iter().map(|rec| rec.expansion_order).sum::<f64>();
+    }
+
+    /// The average expansion order over the bins done, if any.
+    pub fn average_n(&self) -> Option<f64> {
+        (self.num_bins_done > 0).then(|| self.sum_n / self.num_bins_done as f64)
+    }
+}
+
+/// Schedule of a Monte Carlo run
+#[derive(Clone, Copy, Debug)]
+pub struct Schedule {
+    /// The number of MC steps for thermalization.
+    pub num_thm: usize,
+    /// The number of MC steps in each bin.
+    pub num_stat: usize,
+    /// The number of bins.
+    pub num_bins: usize,
+}
+
+/// Checkpointing and early stop of a run
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Control<'a> {
+    /// Saved after every bin, resumed from if it exists.
+    pub checkpoint_path: Option<&'a str>,
+    /// Stop before the next bin (or thermalization steps) would exceed it.
+    pub max_runtime: Option<Duration>,
+    /// Stop once "STOP" appears in the target directory.
+    pub stop_file: bool,
+    /// Watched for "STOP" instead of the target directory, by the runs of a
+    /// sweep, a disorder average or parallel chains, which tell how to
+    /// resume themselves.
+    pub stop_dir: Option<&'a str>,
+    /// Shared by runs in parallel: set when one of them stops, which stops
+    /// the others after their current bin.
+    pub stop_flag: Option<&'a AtomicBool>,
+    /// Print neither the bins nor the analysis.
+    pub quiet: bool,
+}
+
+/// Thermalize the (initialized) model, then measure "num_bins" bins and
+/// append each of them to the ".dat" files in "target_dir"
+/// The simulation mode is recorded in "mode.dat"; at finite temperature
+/// the Renyi-2 mutual information between the two halves is written to
+/// "mutual_info2.dat". The Renyi-2 entropies of the user-defined regions
+/// go to the columns of "renyi2_regions.dat", in the order of "regions.dat",
+/// and the profile S2(l) of the cuts [0, l), l = 1, ..., L - 1, to the
+/// columns of "renyi2_profile.dat".
+/// Each further correlator "PQ" goes to "pq_corr_2.dat", like "zz_corr_2.dat".
+/// The estimates of tr(rho P)^2 for the Pauli strings go to the columns of
+/// "pauli_strings_2.dat", in the order of "pauli_strings.dat", and their
+/// means and errors over the bins of this run to "pauli_strings_estimate.dat".
+/// With the all-pairs correlators, the translation averages C(d) are
+/// appended to "zz_corr_2_dist.dat" and "xx_corr_2_dist.dat", and the
+/// L x L matrices averaged over the bins of this run are written to
+/// "zz_corr_2_matrix.dat" and "xx_corr_2_matrix.dat" (one row per site),
+/// with their errors in "zz_corr_2_matrix_err.dat" and "xx_corr_2_matrix_err.dat".
+/// At the end, the binning analysis of every observable is printed and
+/// saved to "summary.dat" and "rebinning.dat", and the jackknife estimates
+/// of the entropies, the mutual information and the purity ratio (finite
+/// temperature) to "jackknife.dat" (see stats.rs). The per-step time series of
+/// the purities (of the regions as well), the expansion order, the
+/// site-averaged correlators (the further ones as well) and tr(rho P)^2 of the
+/// Pauli strings give their integrated autocorrelation times in
+/// "autocorrelation.dat", from blocking accumulators of a fixed size per
+/// observable (the correlators of the single sites and the cuts get none).
+/// With "checkpoint_path", the state is saved there after the thermalization
+/// and after every bin, and a run finding that file resumes from it.
+/// When the runtime would exceed "max_runtime" with one more bin (or the
+/// next THM_POLL thermalization steps), or a "STOP" file appears in
+/// "target_dir" or "stop_dir" (then removed), the run stops after the current bin (or
+/// those steps), saves a checkpoint ("checkpoint.bin" in "target_dir"
+/// unless "checkpoint_path" is given) and returns the bins so far, without
+/// the final analysis.
+pub fn run(model: &mut TFIModel, schedule: Schedule, target_dir: &str, control: Control) -> Result<Outcome> {
+    let start_time = Instant::now();
+    let checkpoint_path = control.checkpoint_path;
+    let stop_path = checkpoint_path.map_or_else(|| format!("{}/checkpoint.bin", target_dir), String::from);
+    let finite_temperature = model.mode() == SimulationMode::FiniteTemperature;
+    let mut appended: Vec<String> = Vec::new();     // the ".dat" files growing bin by bin
+    aux::write_file(
+        format!("{}/mode.dat", target_dir),
+        format!("mode\t{}\nbeta\t{}\nbeta_sse\t{}\n", model.mode(), model.beta(), 2.0 * model.beta()),
+    )?;
+
+    let mut file_purity: DatFile = open_appended(&mut appended, format!("{}/purity.dat", target_dir))?;
+    let mut file_renyi_ee: DatFile = open_appended(&mut appended, format!("{}/renyi2_ee.dat", target_dir))?;
+    let mut file_zz: DatFile = open_appended(&mut appended, format!("{}/zz_corr_2.dat", target_dir))?;
+    let mut file_xx: DatFile = open_appended(&mut appended, format!("{}/xx_corr_2.dat", target_dir))?;
+    let mut file_profile: DatFile = open_appended(&mut appended, format!("{}/renyi2_profile.dat", target_dir))?;
+    let mut file_pauli_strings: Option<DatFile> = if model.pauli_strings().is_empty() {
+        None
+    } else {
+        let mut content = String::from("# column  Pauli string\n");
+        for (k, pauli_string) in model.pauli_strings().iter().enumerate() {
+            content.push_str(&format!("{}\t{}\n", k, pauli_string));
+        }
+        aux::write_file(format!("{}/pauli_strings.dat", target_dir), content)?;
+        Some(open_appended(&mut appended, format!("{}/pauli_strings_2.dat", target_dir))?)
+    };
+    let mut files_pauli: Vec<DatFile> = model.pauli_pairs().iter()
+        .map(|pair| open_appended(&mut appended, format!("{}/{}.dat", target_dir, pair.file_stem())))
+        .collect::<Result<_>>()?;
+    let mut files_dist: Option<(DatFile, DatFile)> = if model.corr_matrix() {
+        Some((
+            open_appended(&mut appended, format!("{}/zz_corr_2_dist.dat", target_dir))?,
+            open_appended(&mut appended, format!("{}/xx_corr_2_dist.dat", target_dir))?,
+        ))
+    } else {
+        None
+    };
+    let mut file_mutual_info: Option<DatFile> = if finite_temperature {
+        Some(open_appended(&mut appended, format!("{}/mutual_info2.dat", target_dir))?)
+    } else {
+        None
+    };
+    let mut file_regions: Option<DatFile> = if model.regions().is_empty() {
+        None
+    } else {
+        let mut content = String::from("# column  name  sites\n");
+        for (r, region) in model.regions().iter().enumerate() {
+            let sites: Vec<String> = region.sites.iter().map(|s| s.to_string()).collect();
+            content.push_str(&format!("{}\t{}\t{}\n", r, region.name, sites.join(",")));
+        }
+        aux::write_file(format!("{}/regions.dat", target_dir), content)?;
+        Some(open_appended(&mut appended, format!("{}/renyi2_regions.dat", target_dir))?)
+    };
+
+    // ----------------------------------------------------------------
+    //  Resume from the checkpoint if there is one, else thermalize
+    // ----------------------------------------------------------------
+    let mut progress: Progress = match checkpoint_path {
+        Some(path) if Path::new(path).exists() => {
+            let progress = checkpoint::load(path, model, schedule)?;
+            for (file_path, len) in progress.file_lengths.iter() {
+                OpenOptions::new()
+                    .write(true)
+                    .open(file_path)
+                    .and_then(|file| file.set_len(*len))
+                    .map_err(Error::io(file_path))?;
+            }
+            if progress.num_thm_done < schedule.num_thm {
+                println!("\t---> Resuming from \"{}\" after {} thermalization steps", path, progress.num_thm_done);
+            } else {
+                println!("\t---> Resuming from \"{}\" after {} bins", path, progress.num_bins_done);
+            }
+            progress
+        }
+        _ => {
+            let mut progress = Progress::new();
+            progress.series = series_names(model).iter().map(|name| TimeSeries::new(name)).collect();
+            progress
+        }
+    };
+    if progress.num_thm_done < schedule.num_thm {
+        if !control.quiet {
+            println!("\t---> Thermalizing...");
+        }
+        let mut max_poll_time = Duration::ZERO;
+        while progress.num_thm_done < schedule.num_thm {
+            let poll_start = Instant::now();
+            let num_steps = THM_POLL.min(schedule.num_thm - progress.num_thm_done);
+            for _ in 0..num_steps {
+                model.mc_thermalizing();
+            }
+            progress.num_thm_done += num_steps;
+
+            max_poll_time = max_poll_time.max(poll_start.elapsed());
+            if progress.num_thm_done < schedule.num_thm
+                && let Some(reason) = stop_reason(&control, target_dir, start_time.elapsed() + max_poll_time, "thermalization steps")
+            {
+                let done = format!("{} of {} thermalization steps ({})", progress.num_thm_done, schedule.num_thm, reason);
+                stop(&stop_path, &control, model, schedule, &mut progress, &appended, &done)?;
+                return Ok(Outcome { records: progress.records, num_thm_done: progress.num_thm_done });
+            }
+        }
+        if let Some(path) = checkpoint_path {
+            save_checkpoint(path, model, schedule, &mut progress, &appended)?;
+        }
+    }
+
+    // notice that we utilize samples in time slices, thus "num_stat" can be modified
+    let num_samples: f64 = schedule.num_stat as f64 * model.m() as f64;    // use f64 to avoid the overflow
+    if !control.quiet {
+        println!("\t---> Maximum cut-off = {}", model.m());
+        println!("\t---> Total number of samples = {} * {} = {} ", schedule.num_stat, model.m(), num_samples);
+        println!("\t---> Sampling and measuring...");
+    }
+
+    let num_sites = model.num_sites() as f64;
+    let mut max_bin_time = Duration::ZERO;
+    for b in progress.num_bins_done..schedule.num_bins {
+        let bin_start = Instant::now();
+        let series = &mut progress.series;     // one value per MC step, in the order of SERIES
+        let stats = &mut progress.stats;
+        if !control.quiet {
+            println!("\t\t# bin {}...", b);
+        }
+        model.ini_measure();
+        let mut sum_n: f64 = 0.0;
+        for _ in 0..schedule.num_stat {
+            // the accumulators grow by the sum over the time slices of this step
+            let purity = model.purity();
+            let partial_purity = model.partial_purity();
+            let zz_corr_2: f64 = model.zz_corr_2().iter().sum();
+            let xx_corr_2: f64 = model.xx_corr_2().iter().sum();
+            let further = further_accumulators(model);
+            model.mc_sampling();
+
+            let m = model.m() as f64;
+            series[0].push((model.purity() - purity) / m);
+            series[1].push((model.partial_purity() - partial_purity) / m);
+            series[2].push(model.expansion_order() as f64);
+            sum_n += model.expansion_order() as f64;
+            series[3].push((model.zz_corr_2().iter().sum::<f64>() - zz_corr_2) / (m * num_sites));
+            series[4].push((model.xx_corr_2().iter().sum::<f64>() - xx_corr_2) / (m * num_sites));
+            for (s, (after, before)) in series[SERIES.len()..].iter_mut().zip(further_accumulators(model).iter().zip(&further)) {
+                s.push((after - before) / m);
+            }
+        }
+        model.statisticize();
+
+        // ------------------------------------
+        //  Saving the data
+        // ------------------------------------
+        file_purity.write_str(&format!("{:<16.10}\n", model.purity()))?;
+        file_renyi_ee.write_str(&format!("{:<16.10}\n", -model.partial_purity().ln()))?;
+        file_zz.write_str(&format!("{}\n", aux::format_row(model.zz_corr_2())))?;
+        file_xx.write_str(&format!("{}\n", aux::format_row(model.xx_corr_2())))?;
+        let profile: Vec<f64> = model.profile_purity().iter().map(|p| -p.ln()).collect();
+        file_profile.write_str(&format!("{}\n", aux::format_row(&profile)))?;
+        if let Some(file) = file_pauli_strings.as_mut() {
+            file.write_str(&format!("{}\n", aux::format_row(model.pauli_string_2())))?;
+        }
+        for (file, corr) in files_pauli.iter_mut().zip(model.pauli_corr_2()) {
+            file.write_str(&format!("{}\n", aux::format_row(corr)))?;
+        }
+        if let Some((file_zz_dist, file_xx_dist)) = files_dist.as_mut() {
+            let zz_dist = model.lattice().translation_average(model.zz_corr_matrix());
+            let xx_dist = model.lattice().translation_average(model.xx_corr_matrix());
+            file_zz_dist.write_str(&format!("{}\n", aux::format_row(&zz_dist)))?;
+            file_xx_dist.write_str(&format!("{}\n", aux::format_row(&xx_dist)))?;
+        }
+        if let Some(file) = file_mutual_info.as_mut() {
+            // I2(A:B) = S2(A) + S2(B) - S2(AB)
+            let mutual_info = -model.partial_purity().ln() - model.complement_purity().ln() + model.purity().ln();
+            file.write_str(&format!("{:<16.10}\n", mutual_info))?;
+        }
+        if let Some(file) = file_regions.as_mut() {
+            let renyi2: Vec<f64> = model.region_purity().iter().map(|p| -p.ln()).collect();
+            file.write_str(&format!("{}\n", aux::format_row(&renyi2)))?;
+        }
+
+        let record = BinRecord {
+            expansion_order: sum_n / schedule.num_stat as f64,
+            purity: model.purity(),
+            partial_purity: model.partial_purity(),
+            complement_purity: model.complement_purity(),
+            region_purity: model.region_purity().to_vec(),
+            profile_purity: model.profile_purity().to_vec(),
+            zz_corr_2: model.zz_corr_2().to_vec(),
+            xx_corr_2: model.xx_corr_2().to_vec(),
+            pauli_corr_2: model.pauli_corr_2().to_vec(),
+            pauli_string_2: model.pauli_string_2().to_vec(),
+            zz_corr_matrix: model.zz_corr_matrix().to_vec(),
+            xx_corr_matrix: model.xx_corr_matrix().to_vec(),
+        };
+        add_bin(stats, model, &record);
+        progress.records.push(record);
+        progress.num_bins_done = b + 1;
+        if let Some(path) = checkpoint_path {
+            save_checkpoint(path, model, schedule, &mut progress, &appended)?;
+        }
+
+        // ---------------------------------------------
+        //  Stop early, keeping a checkpoint to resume
+        // ---------------------------------------------
+        max_bin_time = max_bin_time.max(bin_start.elapsed());
+        if b + 1 < schedule.num_bins
+            && let Some(reason) = stop_reason(&control, target_dir, start_time.elapsed() + max_bin_time, "bin")
+        {
+            let done = format!("{} of {} bins ({})", b + 1, schedule.num_bins, reason);
+            stop(&stop_path, &control, model, schedule, &mut progress, &appended, &done)?;
+            return Ok(Outcome { records: progress.records, num_thm_done: progress.num_thm_done });
+        }
+    }
+    let Progress { num_thm_done, records, mut stats, series, .. } = progress;
+    add_derived(&mut stats, model);
+    if !control.quiet {
+        println!("\t---> Binning analysis over {} bins:", schedule.num_bins);
+        stats.print_table();
+        println!("\t---> Integrated autocorrelation times (in MC steps):");
+    }
+    stats.save(target_dir)?;
+    stats::save_autocorrelation(&series, target_dir, schedule.num_stat, !control.quiet)?;
+
+    if !model.pauli_strings().is_empty() {
+        let mut content = String::from("# Pauli string  mean  error\n");
+        for (k, pauli_string) in model.pauli_strings().iter().enumerate() {
+            let samples: Vec<f64> = records.iter().map(|rec| rec.pauli_string_2[k]).collect();
+            let (mean, err) = aux::mean_and_error(&samples);
+            content.push_str(&format!("{}\t{:<16.10}\t{:<16.10}\n", pauli_string.key(), mean, err));
+        }
+        aux::write_file(format!("{}/pauli_strings_estimate.dat", target_dir), content)?;
+    }
+
+    if model.corr_matrix() {
+        let zz_matrices: Vec<&[f64]> = records.iter().map(|rec| rec.zz_corr_matrix.as_slice()).collect();
+        let xx_matrices: Vec<&[f64]> = records.iter().map(|rec| rec.xx_corr_matrix.as_slice()).collect();
+        save_matrix(&zz_matrices, model.num_sites(), &format!("{}/zz_corr_2", target_dir))?;
+        save_matrix(&xx_matrices, model.num_sites(), &format!("{}/xx_corr_2", target_dir))?;
+    }
+
+    Ok(Outcome { records, num_thm_done })
+}
+
+// ----------------------------------------------------------------
+//  The time series of the autocorrelation analysis: SERIES, then
+//  the purity of each region, the site average of each further
+//  correlator and tr(rho P)^2 of each Pauli string
+// ----------------------------------------------------------------
+fn series_names(model: &TFIModel) -> Vec<String> {
+    let mut names: Vec<String> = SERIES.iter().map(|name| name.to_string()).collect();
+    names.extend(model.regions().iter().map(|region| format!("purity_{}", region.name)));
+    names.extend(model.pauli_pairs().iter().map(|pair| format!("{}_avg", pair.file_stem())));
+    names.extend(model.pauli_strings().iter().map(|pauli_string| format!("pauli_{}", pauli_string.key())));
+    names
+}
+
+// the accumulators of the series after SERIES, in the order of "series_names"
+fn further_accumulators(model: &TFIModel) -> Vec<f64> {
+    let num_sites = model.num_sites() as f64;
+    let mut accumulators: Vec<f64> = model.region_purity().to_vec();
+    accumulators.extend(model.pauli_corr_2().iter().map(|corr| corr.iter().sum::<f64>() / num_sites));
+    accumulators.extend_from_slice(model.pauli_string_2());
+    accumulators
+}
+
+// ----------------------------------------------------------------
+//  The bin of every observable of the binning analysis
+// ----------------------------------------------------------------
+fn add_bin(stats: &mut Statistics, model: &TFIModel, rec: &BinRecord) {
+    stats.add("purity", rec.purity);
+    stats.add("partial_purity", rec.partial_purity);
+    stats.add("renyi2_ee", -rec.partial_purity.ln());
+    if model.mode() == SimulationMode::FiniteTemperature {
+        stats.add("complement_purity", rec.complement_purity);
+        stats.add("mutual_info2", -rec.partial_purity.ln() - rec.complement_purity.ln() + rec.purity.ln());
+    }
+    for (region, p) in model.regions().iter().zip(&rec.region_purity) {
+        stats.add(&format!("purity_{}", region.name), *p);
+        stats.add(&format!("renyi2_{}", region.name), -p.ln());
+    }
+    for (pauli_string, &c) in model.pauli_strings().iter().zip(&rec.pauli_string_2) {
+        stats.add(&format!("pauli_{}", pauli_string.key()), c);
+    }
+    for (l, p) in rec.profile_purity.iter().enumerate() {
+        stats.add(&format!("profile_purity[{}]", l + 1), *p);
+        stats.add(&format!("renyi2_profile[{}]", l + 1), -p.ln());
+    }
+    stats.add_row("zz_corr_2", &rec.zz_corr_2);
+    stats.add_row("xx_corr_2", &rec.xx_corr_2);
+    for (pair, corr) in model.pauli_pairs().iter().zip(&rec.pauli_corr_2) {
+        stats.add_row(&pair.file_stem(), corr);
+    }
+}
+
+// --------------------------------------------------------------
+//  Nonlinear functions of the bin averages, by the jackknife
+// --------------------------------------------------------------
+fn add_derived(stats: &mut Statistics, model: &TFIModel) {
+    let renyi2 = |p: &[f64]| -p[0].ln();
+    stats.add_derived("renyi2_ee", &["partial_purity"], renyi2);
+    if model.mode() == SimulationMode::FiniteTemperature {
+        stats.add_derived("renyi2_total", &["purity"], renyi2);
+        stats.add_derived("mutual_info2", &["partial_purity", "complement_purity", "purity"], |p: &[f64]| {
+            -p[0].ln() - p[1].ln() + p[2].ln()
+        });
+        stats.add_derived("purity_ratio", &["partial_purity", "purity"], |p: &[f64]| p[0] / p[1]);
+    }
+    for region in model.regions().iter() {
+        stats.add_derived(&format!("renyi2_{}", region.name), &[&format!("purity_{}", region.name)], renyi2);
+    }
+    for l in 1..model.num_sites() {
+        stats.add_derived(&format!("renyi2_profile[{}]", l), &[&format!("profile_purity[{}]", l)], renyi2);
+    }
+}
+
+// why the run should stop before the "next" unit of work, if it should
+fn stop_reason(control: &Control, target_dir: &str, expected_runtime: Duration, next: &str) -> Option<String> {
+    if control.stop_flag.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+        return Some("a parallel run stopped".to_string());
+    }
+    let found_stop = control.stop_file.then(|| stop_requested(control.stop_dir.unwrap_or(target_dir))).flatten();
+    let reason = found_stop.or_else(|| match control.max_runtime {
+        Some(max_runtime) if expected_runtime > max_runtime => {
+            Some(format!("the next {} would exceed the maximum runtime of {}s", next, max_runtime.as_secs_f64()))
+        }
+        _ => None,
+    });
+    if let (Some(flag), Some(_)) = (control.stop_flag, reason.as_ref()) {
+        flag.store(true, Ordering::Relaxed);
+    }
+    reason
+}
+
+/// Whether a "STOP" file has appeared in `target_dir`, which is removed;
+/// the reason to stop if so. The sweeps and the disorder averages poll it
+/// between their runs.
+pub fn stop_requested(target_dir: &str) -> Option<String> {
+    let stop_path = format!("{}/STOP", target_dir);
+    if !Path::new(&stop_path).exists() {
+        return None;
+    }
+    // a STOP file removed meanwhile stops the run all the same
+    let _ = fs::remove_file(&stop_path);
+    Some(format!("found \"{}\"", stop_path))
+}
+
+// save a checkpoint to "path" to resume a run stopped after "done" (with the
+// reason), and report it
+fn stop(path: &str, control: &Control, model: &TFIModel, schedule: Schedule, progress: &mut Progress, appended: &[String], done: &str) -> Result<()> {
+    save_checkpoint(path, model, schedule, progress, appended)?;
+    println!("\t---> Stopped after {}", done);
+    if control.stop_dir.is_none() {
+        println!("\t---> Resume with \"--checkpoint {}\"", path);
+    }
+    Ok(())
+}
+
+// record the lengths of the appended files, then save the checkpoint
+fn save_checkpoint(path: &str, model: &TFIModel, schedule: Schedule, progress: &mut Progress, appended: &[String]) -> Result<()> {
+    progress.file_lengths = appended
+        .iter()
+        .map(|file_path| Ok((file_path.clone(), fs::metadata(file_path).map_err(Error::io(file_path))?.len())))
+        .collect::<Result<_>>()?;
+    checkpoint::save(path, model, schedule, progress)
+}
+
+fn open_appended(appended: &mut Vec<String>, file_path: String) -> Result<DatFile> {
+    appended.push(file_path.clone());
+    DatFile::append(file_path)
+}
+
+// write the mean and the error over the bins of a row-major n x n matrix
+// to "<prefix>_matrix.dat" and "<prefix>_matrix_err.dat"
+fn save_matrix(bins: &[&[f64]], n: usize, prefix: &str) -> Result<()> {
+    let mut means = String::new();
+    let mut errors = String::new();
+    for i in 0..n {
+        let (mean_row, err_row): (Vec<f64>, Vec<f64>) = (0..n)
+            .map(|j| aux::mean_and_error(&bins.iter().map(|m| m[i * n + j]).collect::<Vec<f64>>()))
+            .unzip();
+        means.push_str(&format!("{}\n", aux::format_row(&mean_row)));
+        errors.push_str(&format!("{}\n", aux::format_row(&err_row)));
+    }
+    aux::write_file(format!("{}_matrix.dat", prefix), means)?;
+    aux::write_file(format!("{}_matrix_err.dat", prefix), errors)
+}
+
+/// Run independent Markov chains (seed, model) in parallel, on at most
+/// available_parallelism() threads taking the chains from a queue, chain "c"
+/// writing its bins to "target_dir/chain_c" and a checkpoint
+/// "chain_c/checkpoint.bin" after every bin
+/// A "STOP" file in "target_dir" (or "max_runtime") stops every chain after
+/// its current bin; run again, the chains resume from their checkpoints
+/// (see output.rs).
+/// The bins done by all chains are merged in "target_dir":
+///     summary.dat, rebinning.dat, jackknife.dat   the analysis of all the bins
+///                                                 (chain after chain, see stats.rs)
+///     chains.dat          c, seed, num_bins, n, purity, S2 of each chain
+///     convergence.dat     observable, num_chains, mean, cross-chain error, R-hat
+/// What the chains have done is returned.
+pub fn run_chains(chains: &mut [(u32, TFIModel)], schedule: Schedule, target_dir: &str, max_runtime: Option<Duration>) -> Result<RunSummary> {
+    let start_time = Instant::now();
+    let num_chains = chains.len();
+    let num_threads = thread::available_parallelism().map_or(1, |n| n.get()).min(num_chains);
+    println!("\t---> Running {} chains in parallel on {} threads...", num_chains, num_threads);
+
+    // the threads take the chains from a shared queue, each result going to its slot;
+    // the first chain to stop stops the others through "stop_flag"
+    let queue = Mutex::new(chains.iter_mut().enumerate());
+    let slots: Vec<Mutex<Option<Result<Outcome>>>> = (0..num_chains).map(|_| Mutex::new(None)).collect();
+    let stop_flag = AtomicBool::new(false);
+    thread::scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                let Some((c, (seed, model))) = next else { break };
+                let control = Control {
+                    max_runtime: max_runtime.map(|max_runtime| max_runtime.saturating_sub(start_time.elapsed())),
+                    stop_file: true,
+                    stop_dir: Some(target_dir),
+                    stop_flag: Some(&stop_flag),
+                    quiet: true,
+                    ..Control::default()
+                };
+                *slots[c].lock().unwrap() = Some(run_chain(c, *seed, model, schedule, target_dir, control));
+            });
+        }
+    });
+    let outcomes: Vec<Outcome> = slots
+        .into_iter()
+        .map(|slot| slot.into_inner().unwrap().expect("every chain is run"))
+        .collect::<Result<_>>()?;
+    let mut summary = RunSummary::default();
+    for ((_, model), outcome) in chains.iter().zip(&outcomes) {
+        summary.add(model, outcome, schedule.num_bins);
+    }
+    if summary.stopped {
+        println!("\t---> Stopped after {} of {} bins: run again to resume", summary.num_bins_done, num_chains * schedule.num_bins);
+    }
+    if summary.num_bins_done == 0 {
+        return Ok(summary);
+    }
+
+    // ---------------------------------------------------------
+    //  The statistics of each chain, and of all bins merged
+    // ---------------------------------------------------------
+    let model = &chains[0].1;
+    let mut merged = Statistics::new();
+    let mut per_chain: Vec<Statistics> = Vec::with_capacity(num_chains);
+    let mut content = String::from("# chain  seed  num_bins  n  purity  renyi2_ee\n");
+    for (c, ((seed, _), outcome)) in chains.iter().zip(&outcomes).enumerate() {
+        let chain_records = &outcome.records;
+        let mut stats = Statistics::new();
+        for rec in chain_records.iter() {
+            add_bin(&mut stats, model, rec);
+            add_bin(&mut merged, model, rec);
+        }
+        let num_bins = chain_records.len() as f64;
+        let mean = |f: fn(&BinRecord) -> f64| chain_records.iter().map(f).sum::<f64>() / num_bins;
+        content.push_str(&format!(
+            "{}\t{}\t{}\t{:<16.10}\t{:<16.10}\t{:<16.10}\n",
+            c, seed, chain_records.len(), mean(|rec| rec.expansion_order), mean(|rec| rec.purity),
+            -mean(|rec| rec.partial_purity).ln()
+        ));
+        per_chain.push(stats);
+    }
+    aux::write_file(format!("{}/chains.dat", target_dir), content)?;
+    add_derived(&mut merged, model);
+    println!("\t---> Binning analysis over the {} bins of {} chains:", summary.num_bins_done, num_chains);
+    merged.print_table();
+    merged.save(target_dir)?;
+
+    // ---------------------------------------------------------
+    //  Cross-chain errors and the R-hat diagnostic
+    // ---------------------------------------------------------
+    let mut content = String::from("# observable  num_chains  mean  cross_chain_error  r_hat\n");
+    println!("\t---> Convergence of the chains:");
+    println!("\t{:<24} {:>18} {:>14} {:>10}", "observable", "mean", "cross-chain", "R-hat");
+    for obs in merged.observables.iter() {
+        let bins: Vec<&[f64]> = per_chain
+            .iter()
+            .filter_map(|stats| stats.get(&obs.name).map(|o| o.bins.as_slice()))
+            .collect();
+        let chain_means: Vec<f64> = bins.iter().map(|b| b.iter().sum::<f64>() / b.len() as f64).collect();
+        let (mean, error) = aux::mean_and_error(&chain_means);
+        let r_hat = stats::gelman_rubin(&bins);
+        content.push_str(&format!("{}\t{}\t{:<16.10}\t{:<16.10}\t{:<16.10}\n", obs.name, bins.len(), mean, error, r_hat));
+        if !obs.name.contains('[') {
+            let warning = if r_hat > 1.1 { "  (not converged)" } else { "" };
+            println!("\t{:<24} {:>18.10} {:>14.3e} {:>10.4}{}", obs.name, mean, error, r_hat, warning);
+        }
+    }
+    aux::write_file(format!("{}/convergence.dat", target_dir), content)?;
+    Ok(summary)
+}
+
+// chain "c" of "run_chains" in "target_dir/chain_c", with its checkpoint there
+fn run_chain(c: usize, seed: u32, model: &mut TFIModel, schedule: Schedule, target_dir: &str, control: Control) -> Result<Outcome> {
+    let chain_dir = format!("{}/chain_{}", target_dir, c);
+    fs::create_dir_all(&chain_dir).map_err(Error::io(&chain_dir))?;
+    let checkpoint_path = format!("{}/checkpoint.bin", chain_dir);
+    let outcome = run(model, schedule, &chain_dir, Control { checkpoint_path: Some(&checkpoint_path), ..control })?;
+    if outcome.records.len() < schedule.num_bins {
+        println!("\t\t# chain {} (seed = {}) stopped after {} of {} bins", c, seed, outcome.records.len(), schedule.num_bins);
+    } else {
+        println!("\t\t# chain {} (seed = {}) done", c, seed);
+    }
+    Ok(outcome)
+}
+
+/// Parameters of the disorder average, besides those of the run
+pub struct DisorderAverage {
+    /// The distribution of the couplings, with the scales J and h of the run.
+    pub disorder: Disorder,
+    /// The seed of the couplings of the realizations.
+    pub disorder_seed: u32,
+    /// The number of realizations.
+    pub num_realizations: usize,
+}
+
+/// Loop over the disorder realizations of the run "para"
+///     realization "r" draws its couplings with derive_seed(disorder_seed, 2r)
+///     and runs its Markov chain with derive_seed(seed, 2r + 1)
+/// The bins of realization "r" are written to "output/realization_r", and
+/// the disorder averages with the sample-to-sample errors to "output":
+///     realizations.dat            r, disorder seed, seed, purity, S2
+///     disorder_average.dat        observable, mean, error (with the I2 of the halves
+///                                 at finite temperature, the S2 of each region and
+///                                 tr(rho P)^2 of each Pauli string)
+///     disorder_zz_corr_2.dat      means (first row) and errors (second row)
+///     disorder_xx_corr_2.dat
+///     disorder_renyi2_profile.dat
+///     disorder_pq_corr_2.dat      for each further correlator "PQ"
+/// Each realization saves a checkpoint "realization_r/checkpoint.bin" after
+/// every bin. A "STOP" file in "output" (or "max-runtime") stops the loop
+/// after the current bin, and the averages are taken over the realizations
+/// done. Run again, the loop revisits those from their checkpoints and
+/// resumes the stopped one (see output.rs). What the realizations have done
+/// is returned.
+pub fn run_disorder_average(para: &SimulationConfig, disorder_average: &DisorderAverage) -> Result<RunSummary> {
+    let DisorderAverage { disorder, num_realizations, .. } = *disorder_average;
+    let (schedule, target_dir) = (para.schedule, para.output.as_str());
+    let num_sites = para.lattice.num_sites;
+    let mut purity: Vec<f64> = Vec::with_capacity(num_realizations);
+    let mut renyi2: Vec<f64> = Vec::with_capacity(num_realizations);
+    let mut mutual_info2: Vec<f64> = Vec::with_capacity(num_realizations);
+    let mut zz_corr_2: Vec<Vec<f64>> = vec![Vec::with_capacity(num_realizations); num_sites];
+    let mut xx_corr_2: Vec<Vec<f64>> = vec![Vec::with_capacity(num_realizations); num_sites];
+    let mut pauli_corr_2: Vec<Vec<Vec<f64>>> =
+        vec![vec![Vec::with_capacity(num_realizations); num_sites]; para.pauli_pairs.len()];
+    let mut renyi2_profile: Vec<Vec<f64>> = vec![Vec::with_capacity(num_realizations); num_sites - 1];
+    let mut pauli_string_2: Vec<Vec<f64>> = vec![Vec::with_capacity(num_realizations); para.pauli_strings.len()];
+    let mut region_renyi2: Vec<Vec<f64>> = vec![Vec::with_capacity(num_realizations); para.regions.len()];
+
+    // a resumed average rewrites the rows of the realizations done
+    aux::write_file(format!("{}/realizations.dat", target_dir), "")?;
+    let mut file_realizations: DatFile = DatFile::append(format!("{}/realizations.dat", target_dir))?;
+    let start_time = Instant::now();
+    let mut summary = RunSummary::default();
+
+    for r in 0..num_realizations {
+        let disorder_seed = aux::derive_seed(disorder_average.disorder_seed, 2 * r as u32);
+        let seed = aux::derive_seed(para.seed, 2 * r as u32 + 1);
+        println!("■ realization {} / {} (disorder seed = {}, seed = {})", r + 1, num_realizations, disorder_seed, seed);
+
+        let realization_dir = format!("{}/realization_{}", target_dir, r);
+        fs::create_dir_all(&realization_dir).map_err(Error::io(&realization_dir))?;
+
+        let couplings = Couplings::random(&para.lattice, para.j, para.h, disorder, disorder_seed);
+        couplings.save(&format!("{}/couplings.dat", realization_dir))?;
+
+        let mut model = para.build_model(couplings, seed)?;
+        let checkpoint_path = format!("{}/checkpoint.bin", realization_dir);
+        let control = Control {
+            checkpoint_path: Some(&checkpoint_path),
+            max_runtime: para.max_runtime.map(|max_runtime| max_runtime.saturating_sub(start_time.elapsed())),
+            stop_file: true,
+            stop_dir: Some(target_dir),
+            stop_flag: None,
+            quiet: false,
+        };
+        let outcome = run(&mut model, schedule, &realization_dir, control)?;
+        summary.add(&model, &outcome, schedule.num_bins);
+        let records = outcome.records;
+        if records.len() < schedule.num_bins {
+            println!("■ stopped during realization {} of {}: run again to resume", r + 1, num_realizations);
+            break;
+        }
+
+        // ------------------------------------------------------------------
+        //  Average over the bins of this realization, and S2 = -ln(purity)
+        // ------------------------------------------------------------------
+        let num_bins = records.len() as f64;
+        let purity_r = records.iter().map(|rec| rec.purity).sum::<f64>() / num_bins;
+        let renyi2_r = -(records.iter().map(|rec| rec.partial_purity).sum::<f64>() / num_bins).ln();
+        if para.mode == SimulationMode::FiniteTemperature {
+            let complement_renyi2_r = -(records.iter().map(|rec| rec.complement_purity).sum::<f64>() / num_bins).ln();
+            mutual_info2.push(renyi2_r + complement_renyi2_r + purity_r.ln());
+        }
+        for s in 0..num_sites {
+            zz_corr_2[s].push(records.iter().map(|rec| rec.zz_corr_2[s]).sum::<f64>() / num_bins);
+            xx_corr_2[s].push(records.iter().map(|rec| rec.xx_corr_2[s]).sum::<f64>() / num_bins);
+        }
+        for (k, corr) in pauli_corr_2.iter_mut().enumerate() {
+            for (s, c) in corr.iter_mut().enumerate() {
+                c.push(records.iter().map(|rec| rec.pauli_corr_2[k][s]).sum::<f64>() / num_bins);
+            }
+        }
+        for (l, renyi2_cut) in renyi2_profile.iter_mut().enumerate() {
+            renyi2_cut.push(-(records.iter().map(|rec| rec.profile_purity[l]).sum::<f64>() / num_bins).ln());
+        }
+        for (r, renyi2_region) in region_renyi2.iter_mut().enumerate() {
+            renyi2_region.push(-(records.iter().map(|rec| rec.region_purity[r]).sum::<f64>() / num_bins).ln());
+        }
+        for (k, c) in pauli_string_2.iter_mut().enumerate() {
+            c.push(records.iter().map(|rec| rec.pauli_string_2[k]).sum::<f64>() / num_bins);
+        }
+        purity.push(purity_r);
+        renyi2.push(renyi2_r);
+
+        file_realizations.write_str(
+            &format!("{}\t{}\t{}\t{:<16.10}\t{:<16.10}\n", r, disorder_seed, seed, purity_r, renyi2_r)
+        )?;
+
+        if r + 1 < num_realizations
+            && let Some(reason) = stop_requested(target_dir)
+        {
+            println!("■ stopped after {} of {} realizations ({}): run again to resume", r + 1, num_realizations, reason);
+            summary.stopped = true;
+            break;
+        }
+    }
+    let num_realizations = purity.len();
+    if num_realizations == 0 {
+        return Ok(summary);
+    }
+
+    // =========================================================
+    //  Disorder averages with the sample-to-sample errors
+    // =========================================================
+    let (purity_mean, purity_err) = aux::mean_and_error(&purity);
+    let (renyi2_mean, renyi2_err) = aux::mean_and_error(&renyi2);
+    let mut content = format!(
+        "# observable  mean  error\npurity\t{:<16.10}\t{:<16.10}\nrenyi2_ee\t{:<16.10}\t{:<16.10}\n",
+        purity_mean, purity_err, renyi2_mean, renyi2_err
+    );
+    if !mutual_info2.is_empty() {
+        let (mean, err) = aux::mean_and_error(&mutual_info2);
+        content.push_str(&format!("mutual_info2\t{:<16.10}\t{:<16.10}\n", mean, err));
+    }
+    for (region, renyi2_region) in para.regions.iter().zip(&region_renyi2) {
+        let (mean, err) = aux::mean_and_error(renyi2_region);
+        content.push_str(&format!("renyi2_{}\t{:<16.10}\t{:<16.10}\n", region.name, mean, err));
+    }
+    for (pauli_string, c) in para.pauli_strings.iter().zip(&pauli_string_2) {
+        let (mean, err) = aux::mean_and_error(c);
+        content.push_str(&format!("pauli_{}\t{:<16.10}\t{:<16.10}\n", pauli_string.key(), mean, err));
+    }
+    aux::write_file(format!("{}/disorder_average.dat", target_dir), content)?;
+
+    let mut averaged: Vec<(String, &Vec<Vec<f64>>)> = vec![
+        ("zz_corr_2".to_string(), &zz_corr_2),
+        ("xx_corr_2".to_string(), &xx_corr_2),
+        ("renyi2_profile".to_string(), &renyi2_profile),
+    ];
+    averaged.extend(para.pauli_pairs.iter().map(|pair| pair.file_stem()).zip(&pauli_corr_2));
+    for (name, corr) in averaged {
+        let (means, errors): (Vec<f64>, Vec<f64>) = corr.iter().map(|c| aux::mean_and_error(c)).unzip();
+        aux::write_file(
+            format!("{}/disorder_{}.dat", target_dir, name),
+            format!("{}\n{}\n", aux::format_row(&means), aux::format_row(&errors)),
+        )?;
+    }
+
+    println!("■ disorder average over {} realizations:", num_realizations);
+    println!("\tpurity = {:.10} +/- {:.10}", purity_mean, purity_err);
+    println!("\tS2     = {:.10} +/- {:.10}", renyi2_mean, renyi2_err);
+    Ok(summary)
+}
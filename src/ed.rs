@@ -0,0 +1,586 @@
+/*********************************************************************************
+    Exact diagonalization of small lattices, the reference of the sampler
+    -------------------------------------------------------------------
+    The Hamiltonian
+        H = - sum_b J_b Z_i Z_j - sum_i h_i X_i
+    acts on the basis states |b> of the Z basis, bit i of b being site i
+    (1 for Z_i = -1). It is real, and so are the states:
+        ground state    Lanczos with full reorthogonalization from the
+                        uniform state, which stays in the even sector of
+                        prod_i X_i (the sector of the ground state for
+                        h > 0), up to MAX_SITES_GROUND_STATE sites
+        thermal state   rho = exp(-beta H) / Z from the full spectrum
+                        (Householder tridiagonalization and implicit QL),
+                        up to MAX_SITES_THERMAL sites
+    -------------------------------------------------------------------
+    The exact values of the estimates of "measure.rs":
+        purity          tr(rho^2)
+        region_purity   tr(rho_A^2) of any set A of sites
+        pauli_2         tr(rho P)^2 of a product P of Paulis, e.g. the
+                        correlators tr(rho Z_r Z_j)^2; for a repeated site
+                        the factors are multiplied (Z_r Z_r = I)
+    and the distribution of the Bell samples themselves ("bell_distribution").
+    "compare" writes the exact values next to the bin averages of a run
+    to "exact.dat", with the deviations in units of the errors.
+*********************************************************************************/
+use crate::aux;
+use crate::driver::BinRecord;
+use crate::error::{Error, Result};
+use crate::tfim::{Couplings, Lattice, Pauli, SimulationMode, TFIModel};
+
+/// The largest lattice of [`ExactState::ground_state`].
+pub const MAX_SITES_GROUND_STATE: usize = 14;
+/// The largest lattice of [`ExactState::thermal`].
+pub const MAX_SITES_THERMAL: usize = 10;
+
+const LANCZOS_TOLERANCE: f64 = 1e-12;
+const MAX_LANCZOS_STEPS: usize = 400;
+
+enum State {
+    Pure(Vec<f64>),     // the amplitudes psi_b
+    Mixed(Vec<f64>),    // the density matrix, row-major
+}
+
+/// The exact ground state or thermal state of a small lattice
+pub struct ExactState {
+    /// The number of sites of the lattice.
+    pub num_sites: usize,
+    /// The energy of the ground state, or the thermal average.
+    pub energy: f64,
+    state: State,
+}
+
+// ----------------------------------------------------------------
+//  The Hamiltonian of the lattice and its couplings
+// ----------------------------------------------------------------
+struct Hamiltonian {
+    num_sites: usize,
+    bonds: Vec<(usize, usize, f64)>,    // (i, j, J_b)
+    fields: Vec<f64>,                   // h_i
+}
+
+impl Hamiltonian {
+    fn new(lattice: &Lattice, couplings: &Couplings) -> Self {
+        let bonds = lattice.b_sites.iter().zip(&couplings.j).map(|(sites, &j)| (sites[0], sites[1], j)).collect();
+        Self { num_sites: lattice.num_sites, bonds, fields: couplings.h.clone() }
+    }
+
+    fn dim(&self) -> usize {
+        1 << self.num_sites
+    }
+
+    fn diagonal(&self, b: usize) -> f64 {
+        self.bonds
+            .iter()
+            .map(|&(i, j, coupling)| if ((b >> i) ^ (b >> j)) & 1 == 0 { -coupling } else { coupling })
+            .sum()
+    }
+
+    // out = H v
+    fn apply(&self, v: &[f64], out: &mut [f64]) {
+        for b in 0..self.dim() {
+            let mut sum = self.diagonal(b) * v[b];
+            for (i, &h) in self.fields.iter().enumerate() {
+                sum -= h * v[b ^ (1 << i)];
+            }
+            out[b] = sum;
+        }
+    }
+
+    fn dense(&self) -> Vec<Vec<f64>> {
+        let dim = self.dim();
+        let mut matrix = vec![vec![0.0; dim]; dim];
+        for (b, row) in matrix.iter_mut().enumerate() {
+            row[b] = self.diagonal(b);
+            for (i, &h) in self.fields.iter().enumerate() {
+                row[b ^ (1 << i)] -= h;
+            }
+        }
+        matrix
+    }
+}
+
+impl ExactState {
+    /// The state of `mode`: the ground state, or the thermal state at `beta`.
+    pub fn new(lattice: &Lattice, couplings: &Couplings, beta: f64, mode: SimulationMode) -> Result<Self> {
+        match mode {
+            SimulationMode::GroundState => Self::ground_state(lattice, couplings),
+            SimulationMode::FiniteTemperature => Self::thermal(lattice, couplings, beta),
+        }
+    }
+
+    /// Ground state by Lanczos: the Krylov basis is kept and reorthogonalized,
+    /// and the lowest Ritz pair is returned once its residual |H psi - E psi|,
+    /// the next beta times the last component of the Ritz vector, is small
+    pub fn ground_state(lattice: &Lattice, couplings: &Couplings) -> Result<Self> {
+        check_size(lattice, MAX_SITES_GROUND_STATE, "the ground state")?;
+        let hamiltonian = Hamiltonian::new(lattice, couplings);
+        let dim = hamiltonian.dim();
+
+        let mut basis: Vec<Vec<f64>> = vec![vec![1.0 / (dim as f64).sqrt(); dim]];
+        let mut alpha: Vec<f64> = Vec::new();
+        let mut beta: Vec<f64> = Vec::new();
+        let mut w = vec![0.0; dim];
+        let (energy, ritz) = loop {
+            let k = basis.len() - 1;
+            hamiltonian.apply(&basis[k], &mut w);
+            alpha.push(dot(&w, &basis[k]));
+            for q in basis.iter() {
+                let overlap = dot(&w, q);
+                w.iter_mut().zip(q).for_each(|(x, y)| *x -= overlap * y);
+            }
+
+            let (values, vectors) = tridiagonal_eigen(&alpha, &beta);
+            let lowest = (0..values.len()).min_by(|&a, &b| values[a].total_cmp(&values[b])).unwrap();
+            let norm = dot(&w, &w).sqrt();
+            let residual = norm * vectors[lowest][k].abs();
+            let converged = residual < LANCZOS_TOLERANCE * values[lowest].abs().max(1.0);
+            if converged || norm < LANCZOS_TOLERANCE || basis.len() == dim.min(MAX_LANCZOS_STEPS) {
+                break (values[lowest], vectors[lowest].clone());
+            }
+            beta.push(norm);
+            basis.push(w.iter().map(|x| x / norm).collect());
+        };
+
+        let mut psi = vec![0.0; dim];
+        for (q, c) in basis.iter().zip(&ritz) {
+            psi.iter_mut().zip(q).for_each(|(x, y)| *x += c * y);
+        }
+        let norm = dot(&psi, &psi).sqrt();
+        psi.iter_mut().for_each(|x| *x /= norm);
+        Ok(Self { num_sites: lattice.num_sites, energy, state: State::Pure(psi) })
+    }
+
+    /// Thermal state rho = sum_k exp(-beta E_k) |k><k| / Z of each copy
+    pub fn thermal(lattice: &Lattice, couplings: &Couplings, beta: f64) -> Result<Self> {
+        check_size(lattice, MAX_SITES_THERMAL, "the thermal state")?;
+        let hamiltonian = Hamiltonian::new(lattice, couplings);
+        let dim = hamiltonian.dim();
+        let (values, vectors) = symmetric_eigen(hamiltonian.dense());
+
+        let e0 = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let weights: Vec<f64> = values.iter().map(|e| (-beta * (e - e0)).exp()).collect();
+        let z: f64 = weights.iter().sum();
+        let energy = values.iter().zip(&weights).map(|(e, w)| e * w).sum::<f64>() / z;
+
+        let mut rho = vec![0.0; dim * dim];
+        for (v, w) in vectors.iter().zip(&weights) {
+            let w = w / z;
+            if w < f64::EPSILON * 1e-3 {
+                continue;
+            }
+            for (a, &va) in v.iter().enumerate() {
+                let row = &mut rho[a * dim..(a + 1) * dim];
+                row.iter_mut().zip(v).for_each(|(x, &vb)| *x += w * va * vb);
+            }
+        }
+        Ok(Self { num_sites: lattice.num_sites, energy, state: State::Mixed(rho) })
+    }
+
+    /// tr(rho^2), 1 for the ground state.
+    pub fn purity(&self) -> f64 {
+        match &self.state {
+            State::Pure(_) => 1.0,
+            State::Mixed(rho) => rho.iter().map(|x| x * x).sum(),
+        }
+    }
+
+    /// tr(rho_A^2), with `rho_A[a][a'] = sum_c rho[(a, c)][(a', c)]` for the
+    /// states of A (a) and of the rest (c); for a pure state, the smaller
+    /// of the two Gram matrices of `psi[a][c]` gives the same value
+    pub fn region_purity(&self, sites: &[usize]) -> f64 {
+        let mask_a: usize = sites.iter().map(|&s| 1 << s).fold(0, |m, bit| m | bit);
+        let mask_c: usize = ((1 << self.num_sites) - 1) & !mask_a;
+        match &self.state {
+            State::Pure(psi) => {
+                let (small, large) = if mask_a.count_ones() <= mask_c.count_ones() { (mask_a, mask_c) } else { (mask_c, mask_a) };
+                let dim_small = 1 << small.count_ones();
+                let dim_large = 1 << large.count_ones();
+                let mut matrix = vec![0.0; dim_small * dim_large];
+                for (b, &x) in psi.iter().enumerate() {
+                    matrix[gather(b, small) * dim_large + gather(b, large)] = x;
+                }
+                let mut purity = 0.0;
+                for a in 0..dim_small {
+                    for a2 in 0..dim_small {
+                        let row = &matrix[a * dim_large..(a + 1) * dim_large];
+                        let row2 = &matrix[a2 * dim_large..(a2 + 1) * dim_large];
+                        let g = dot(row, row2);
+                        purity += g * g;
+                    }
+                }
+                purity
+            }
+            State::Mixed(rho) => {
+                let dim = 1 << self.num_sites;
+                let dim_a = 1 << mask_a.count_ones();
+                let scatter_a: Vec<usize> = (0..dim_a).map(|a| scatter(a, mask_a)).collect();
+                let mut rho_a = vec![0.0; dim_a * dim_a];
+                for b in 0..dim {
+                    let (a, rest) = (gather(b, mask_a), b & mask_c);
+                    for (a2, &bits) in scatter_a.iter().enumerate() {
+                        rho_a[a * dim_a + a2] += rho[b * dim + (bits | rest)];
+                    }
+                }
+                rho_a.iter().map(|x| x * x).sum()
+            }
+        }
+    }
+
+    /// tr(rho P)^2 for P = prod of the factors (site, Pauli). Written as
+    /// P = i^k X^x Z^z, tr(rho P) = i^k sum_b (-1)^{z.b} rho[b][b ^ x],
+    /// the sum being real
+    pub fn pauli_2(&self, ops: &[(usize, Pauli)]) -> f64 {
+        let (mut x_mask, mut z_mask, mut k) = (0usize, 0usize, 0u32);
+        for &(s, sigma) in ops.iter() {
+            let (z, x) = ((sigma.code() >> 1) & 1, sigma.code() & 1);
+            // Y = i X Z, and moving X_s to the left of the Z_s already there gives (-1)
+            k += (z & x) as u32;
+            if x == 1 && (z_mask >> s) & 1 == 1 {
+                k += 2;
+            }
+            x_mask ^= (x as usize) << s;
+            z_mask ^= (z as usize) << s;
+        }
+        let sign = |b: usize| if (z_mask & b).count_ones().is_multiple_of(2) { 1.0 } else { -1.0 };
+        let sum: f64 = match &self.state {
+            State::Pure(psi) => psi.iter().enumerate().map(|(b, x)| sign(b) * x * psi[b ^ x_mask]).sum(),
+            State::Mixed(rho) => {
+                let dim = 1 << self.num_sites;
+                (0..dim).map(|b| sign(b) * rho[b * dim + (b ^ x_mask)]).sum()
+            }
+        };
+        // (i^k)^2 = (-1)^k
+        if k % 2 == 0 { sum * sum } else { -sum * sum }
+    }
+
+    /// The distribution tr(rho P rho P) / 2^L of the Bell samples P from
+    /// rho ⊗ rho (tr(rho P)^2 / 2^L for a pure state), indexed by
+    /// "bell_index". With P = i^k X^x Z^z,
+    /// ```text
+    ///     tr(rho P rho P) = sum_d (-1)^{z.d} sum_c rho[c][c ^ d] rho[c ^ d ^ x][c ^ x]
+    /// ```
+    /// so that all z of one x follow from a Walsh-Hadamard transform
+    pub fn bell_distribution(&self) -> Vec<f64> {
+        let dim = 1usize << self.num_sites;
+        let mut distribution = vec![0.0; dim * dim];
+        let mut f = vec![0.0; dim];
+        for x in 0..dim {
+            match &self.state {
+                State::Pure(psi) => f.iter_mut().enumerate().for_each(|(b, y)| *y = psi[b] * psi[b ^ x]),
+                State::Mixed(rho) => f.iter_mut().enumerate().for_each(|(d, y)| {
+                    *y = (0..dim).map(|c| rho[c * dim + (c ^ d)] * rho[(c ^ d ^ x) * dim + (c ^ x)]).sum();
+                }),
+            }
+            walsh_hadamard(&mut f);
+            for (z, &y) in f.iter().enumerate() {
+                let trace = match &self.state {
+                    State::Pure(_) => y * y,
+                    State::Mixed(_) => y,
+                };
+                let qudits: Vec<u8> = (0..self.num_sites).map(|s| ((((z >> s) & 1) << 1) | ((x >> s) & 1)) as u8).collect();
+                distribution[bell_index(&qudits)] = trace / dim as f64;
+            }
+        }
+        distribution
+    }
+
+    /// tr(rho sigma_r sigma'_j)^2 for j = 0, ..., num_sites - 1.
+    pub fn corr_2(&self, sigma: Pauli, sigma_j: Pauli, r: usize) -> Vec<f64> {
+        (0..self.num_sites).map(|j| self.pauli_2(&[(r, sigma), (j, sigma_j)])).collect()
+    }
+}
+
+fn check_size(lattice: &Lattice, max_sites: usize, what: &str) -> Result<()> {
+    if lattice.num_sites > max_sites {
+        return Err(Error::Config(format!(
+            "exact diagonalization of {} is limited to {} sites, {} has {}", what, max_sites, lattice, lattice.num_sites
+        )));
+    }
+    Ok(())
+}
+
+/// The index of a Bell sample, qudit s in the bits 2s (s^x) and 2s + 1 (s^z).
+pub fn bell_index(qudits: &[u8]) -> usize {
+    qudits.iter().enumerate().map(|(s, &qudit)| (qudit as usize) << (2 * s)).sum()
+}
+
+// f[z] = sum_b (-1)^{z.b} f[b], in place
+fn walsh_hadamard(f: &mut [f64]) {
+    let mut h = 1;
+    while h < f.len() {
+        for block in f.chunks_mut(2 * h) {
+            let (left, right) = block.split_at_mut(h);
+            for (a, b) in left.iter_mut().zip(right.iter_mut()) {
+                (*a, *b) = (*a + *b, *a - *b);
+            }
+        }
+        h *= 2;
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// the bits of "b" in "mask", packed to the right
+fn gather(b: usize, mask: usize) -> usize {
+    let (mut out, mut k, mut m) = (0, 0, mask);
+    while m != 0 {
+        let s = m.trailing_zeros();
+        out |= ((b >> s) & 1) << k;
+        k += 1;
+        m &= m - 1;
+    }
+    out
+}
+
+// the inverse of "gather"
+fn scatter(a: usize, mask: usize) -> usize {
+    let (mut out, mut k, mut m) = (0, 0, mask);
+    while m != 0 {
+        let s = m.trailing_zeros();
+        out |= ((a >> k) & 1) << s;
+        k += 1;
+        m &= m - 1;
+    }
+    out
+}
+
+// =====================================================================
+//  Eigenvalues and eigenvectors (one per row) of a real symmetric matrix:
+//  Householder tridiagonalization ("tred2"), then "tql2"
+// =====================================================================
+pub(crate) fn symmetric_eigen(mut v: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = v.len();
+    let mut d: Vec<f64> = v[n - 1].clone();
+    let mut e = vec![0.0; n];
+    for i in (1..n).rev() {
+        let scale: f64 = d[..i].iter().map(|x| x.abs()).sum();
+        let mut h = 0.0;
+        if scale == 0.0 {
+            e[i] = d[i - 1];
+            for j in 0..i {
+                d[j] = v[i - 1][j];
+                v[i][j] = 0.0;
+                v[j][i] = 0.0;
+            }
+        } else {
+            for x in d[..i].iter_mut() {
+                *x /= scale;
+                h += *x * *x;
+            }
+            let f = d[i - 1];
+            let g = if f > 0.0 { -h.sqrt() } else { h.sqrt() };
+            e[i] = scale * g;
+            h -= f * g;
+            d[i - 1] = f - g;
+            e[..i].iter_mut().for_each(|x| *x = 0.0);
+            for j in 0..i {
+                let f = d[j];
+                v[j][i] = f;
+                let mut g = e[j] + v[j][j] * f;
+                for k in j + 1..i {
+                    g += v[k][j] * d[k];
+                    e[k] += v[k][j] * f;
+                }
+                e[j] = g;
+            }
+            let mut f = 0.0;
+            for j in 0..i {
+                e[j] /= h;
+                f += e[j] * d[j];
+            }
+            let hh = f / (h + h);
+            for j in 0..i {
+                e[j] -= hh * d[j];
+            }
+            for j in 0..i {
+                let (f, g) = (d[j], e[j]);
+                for k in j..i {
+                    v[k][j] -= f * e[k] + g * d[k];
+                }
+                d[j] = v[i - 1][j];
+                v[i][j] = 0.0;
+            }
+        }
+        d[i] = h;
+    }
+
+    // accumulate the transformations
+    for i in 0..n - 1 {
+        v[n - 1][i] = v[i][i];
+        v[i][i] = 1.0;
+        let h = d[i + 1];
+        if h != 0.0 {
+            for k in 0..=i {
+                d[k] = v[k][i + 1] / h;
+            }
+            for j in 0..=i {
+                let g: f64 = (0..=i).map(|k| v[k][i + 1] * v[k][j]).sum();
+                for k in 0..=i {
+                    v[k][j] -= g * d[k];
+                }
+            }
+        }
+        for row in v[..=i].iter_mut() {
+            row[i + 1] = 0.0;
+        }
+    }
+    for j in 0..n {
+        d[j] = v[n - 1][j];
+        v[n - 1][j] = 0.0;
+    }
+    v[n - 1][n - 1] = 1.0;
+    e[0] = 0.0;
+
+    // the columns of "v" become the rows of "z" for the rotations of tql2
+    let mut z: Vec<Vec<f64>> = (0..n).map(|j| v.iter().map(|row| row[j]).collect()).collect();
+    tql2(&mut d, &mut e, &mut z);
+    (d, z)
+}
+
+// the eigenpairs of the symmetric tridiagonal matrix (alpha on the diagonal, beta next to it)
+fn tridiagonal_eigen(alpha: &[f64], beta: &[f64]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = alpha.len();
+    let mut d = alpha.to_vec();
+    let mut e = vec![0.0; n];
+    e[1..n].copy_from_slice(&beta[..n - 1]);
+    let mut z: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    tql2(&mut d, &mut e, &mut z);
+    (d, z)
+}
+
+// ------------------------------------------------------------------
+//  Implicit QL on the tridiagonal matrix (d, e[1..]), rotating the rows
+//  of "z": row k ends as the eigenvector of d[k]
+// ------------------------------------------------------------------
+fn tql2(d: &mut [f64], e: &mut [f64], z: &mut [Vec<f64>]) {
+    let n = d.len();
+    for i in 1..n {
+        e[i - 1] = e[i];
+    }
+    e[n - 1] = 0.0;
+    let (mut f, mut tst1) = (0.0f64, 0.0f64);
+    for l in 0..n {
+        tst1 = tst1.max(d[l].abs() + e[l].abs());
+        let mut m = l;
+        while m < n - 1 && e[m].abs() > f64::EPSILON * tst1 {
+            m += 1;
+        }
+        if m > l {
+            loop {
+                let mut g = d[l];
+                let mut p = (d[l + 1] - g) / (2.0 * e[l]);
+                let mut r = p.hypot(1.0);
+                if p < 0.0 {
+                    r = -r;
+                }
+                d[l] = e[l] / (p + r);
+                d[l + 1] = e[l] * (p + r);
+                let dl1 = d[l + 1];
+                let mut h = g - d[l];
+                for x in d[l + 2..].iter_mut() {
+                    *x -= h;
+                }
+                f += h;
+
+                p = d[m];
+                let (mut c, mut c2, mut c3) = (1.0, 1.0, 1.0);
+                let el1 = e[l + 1];
+                let (mut s, mut s2) = (0.0, 0.0);
+                for i in (l..m).rev() {
+                    c3 = c2;
+                    c2 = c;
+                    s2 = s;
+                    g = c * e[i];
+                    h = c * p;
+                    r = p.hypot(e[i]);
+                    e[i + 1] = s * r;
+                    s = e[i] / r;
+                    c = p / r;
+                    p = c * d[i] - s * g;
+                    d[i + 1] = h + s * (c * g + s * d[i]);
+                    let (head, tail) = z.split_at_mut(i + 1);
+                    for (zi, zi1) in head[i].iter_mut().zip(tail[0].iter_mut()) {
+                        let h = *zi1;
+                        *zi1 = s * *zi + c * h;
+                        *zi = c * *zi - s * h;
+                    }
+                }
+                p = -s * s2 * c3 * el1 * e[l] / dl1;
+                e[l] = s * p;
+                d[l] = c * p;
+                if e[l].abs() <= f64::EPSILON * tst1 {
+                    break;
+                }
+            }
+        }
+        d[l] += f;
+        e[l] = 0.0;
+    }
+}
+
+/// The exact values next to the bin averages of a run, in "exact.dat":
+/// ```text
+///     observable, exact, mean, error, (mean - exact) / error
+/// ```
+/// for the purities, the profile, the regions, the correlators with the
+/// reference site and the Pauli strings of "model"
+pub fn compare(exact: &ExactState, model: &TFIModel, records: &[BinRecord], target_dir: &str) -> Result<()> {
+    let num_sites = model.num_sites();
+    let mut rows: Vec<(String, f64, Vec<f64>)> = Vec::new();
+    let all: Vec<usize> = (0..num_sites).collect();
+    let bins = |f: &dyn Fn(&BinRecord) -> f64| -> Vec<f64> { records.iter().map(f).collect() };
+
+    rows.push(("purity".to_string(), exact.purity(), bins(&|rec| rec.purity)));
+    rows.push(("partial_purity".to_string(), exact.region_purity(&all[..num_sites / 2]), bins(&|rec| rec.partial_purity)));
+    if model.mode() == SimulationMode::FiniteTemperature {
+        rows.push(("complement_purity".to_string(), exact.region_purity(&all[num_sites / 2..]), bins(&|rec| rec.complement_purity)));
+    }
+    for (r, region) in model.regions().iter().enumerate() {
+        rows.push((format!("purity_{}", region.name), exact.region_purity(&region.sites), bins(&|rec| rec.region_purity[r])));
+    }
+    for l in 1..num_sites {
+        rows.push((format!("profile_purity[{}]", l), exact.region_purity(&all[..l]), bins(&|rec| rec.profile_purity[l - 1])));
+    }
+    let ref_site = model.ref_site();
+    let mut pairs: Vec<(String, Pauli, Pauli)> = vec![("zz_corr_2".to_string(), Pauli::Z, Pauli::Z), ("xx_corr_2".to_string(), Pauli::X, Pauli::X)];
+    pairs.extend(model.pauli_pairs().iter().map(|pair| (pair.file_stem(), pair.0, pair.1)));
+    for (k, (name, sigma, sigma_j)) in pairs.iter().enumerate() {
+        for (j, value) in exact.corr_2(*sigma, *sigma_j, ref_site).into_iter().enumerate() {
+            let samples = match k {
+                0 => bins(&|rec| rec.zz_corr_2[j]),
+                1 => bins(&|rec| rec.xx_corr_2[j]),
+                _ => bins(&|rec| rec.pauli_corr_2[k - 2][j]),
+            };
+            rows.push((format!("{}[{}]", name, j), value, samples));
+        }
+    }
+    for (k, pauli_string) in model.pauli_strings().iter().enumerate() {
+        rows.push((format!("pauli_{}", pauli_string.key()), exact.pauli_2(&pauli_string.ops), bins(&|rec| rec.pauli_string_2[k])));
+    }
+
+    let mut content = String::from("# observable  exact  mean  error  deviation\n");
+    let mut max_deviation: (f64, &str) = (0.0, "");
+    for (name, value, samples) in rows.iter() {
+        let (mean, error) = aux::mean_and_error(samples);
+        // an exact estimate (e.g. Z_r Z_r = I) has no error and no deviation
+        let deviation = if error > 0.0 { (mean - value) / error } else { 0.0 };
+        content.push_str(&format!("{}\t{:<16.10}\t{:<16.10}\t{:<16.10}\t{:<16.10}\n", name, value, mean, error, deviation));
+        if deviation.abs() > max_deviation.0.abs() {
+            max_deviation = (deviation, name);
+        }
+    }
+    aux::write_file(format!("{}/exact.dat", target_dir), content)?;
+
+    println!("\t---> Exact diagonalization (energy = {:.10}):", exact.energy);
+    println!("\t{:<24} {:>18} {:>18} {:>14} {:>10}", "observable", "exact", "mean", "error", "deviation");
+    for (name, value, samples) in rows.iter().filter(|(name, _, _)| !name.contains('[')) {
+        let (mean, error) = aux::mean_and_error(samples);
+        let deviation = if error > 0.0 { (mean - value) / error } else { 0.0 };
+        println!("\t{:<24} {:>18.10} {:>18.10} {:>14.3e} {:>10.2}", name, value, mean, error, deviation);
+    }
+    println!("\t{} observables, the largest deviation being {:.2} errors ({})", rows.len(), max_deviation.0, max_deviation.1);
+    Ok(())
+}
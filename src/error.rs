@@ -0,0 +1,72 @@
+/*********************************************************************************
+    Errors of a run
+    -------------------------------------------------------------------
+        Config          a missing, unparsable or inconsistent parameter
+        Lattice         a lattice too small for its boundary conditions
+        Model           couplings or a temperature the SSE cannot simulate
+        Checkpoint      an unreadable checkpoint, or one of another run
+        InvalidOperator an operator string with an operator of no type
+        Io              a file of "path" that cannot be read or written
+*********************************************************************************/
+use std::fmt;
+use std::io;
+
+/// The errors of a run, each with a message for the user.
+#[derive(Debug)]
+pub enum Error {
+    /// A missing, unparsable or inconsistent parameter.
+    Config(String),
+    /// A lattice too small for its boundary conditions.
+    Lattice(String),
+    /// Couplings or a temperature the SSE cannot simulate.
+    Model(String),
+    /// An unreadable checkpoint, or one of another run.
+    Checkpoint(String),
+    /// An operator of no type at position `p` of the operator string.
+    InvalidOperator {
+        /// The position in the operator string.
+        p: usize,
+        /// The operator found there.
+        op: i32,
+    },
+    /// A file that cannot be read or written.
+    Io {
+        /// The path of the file.
+        path: String,
+        /// The error of the file system.
+        source: io::Error,
+    },
+}
+
+/// The result of the fallible operations of the crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The [`Error::Io`] of `path`, as in
+    /// `fs::write(&path, content).map_err(Error::io(&path))?`.
+    pub fn io(path: &str) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |source| Error::Io { path: path.to_string(), source }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config(msg) => write!(f, "{}", msg),
+            Error::Lattice(msg) => write!(f, "invalid lattice: {}", msg),
+            Error::Model(msg) => write!(f, "invalid model: {}", msg),
+            Error::Checkpoint(msg) => write!(f, "{}", msg),
+            Error::InvalidOperator { p, op } => write!(f, "invalid operator {} at op_string[{}]", op, p),
+            Error::Io { path, source } => write!(f, "\"{}\": {}", path, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+/*********************************************************************************
+    Free-fermion ground state of the chain, the reference of long chains
+    -------------------------------------------------------------------
+    The Jordan-Wigner transformation with the string of X maps the chain
+        H = - sum_b J_b Z_b Z_{b+1} - sum_i h_i X_i
+    to the Majorana fermions
+        g_{2i} = X_0 ... X_{i-1} Z_i,   g_{2i+1} = X_0 ... X_{i-1} Y_i
+    with X_i = i g_{2i} g_{2i+1} and Z_i Z_{i+1} = i g_{2i+1} g_{2i+2}, i.e.
+        H = (i/4) sum_kl A_kl g_k g_l
+    for a real antisymmetric A. The bond closing a periodic chain picks up
+    the parity prod_i X_i, which is +1 in the ground state (h > 0), so it
+    enters A with the opposite sign (antiperiodic fermions).
+    -------------------------------------------------------------------
+    The ground state is the Gaussian state of the covariance matrix
+        G_kl = (i/2) <[g_k, g_l]> = - A (A^T A)^{-1/2}
+    (zero modes, e.g. at h = 0, contribute nothing), and by Wick's theorem
+        <Z_r Z_j>   = Pf G[2r+1 .. 2j]          (r < j)
+        <X_r X_j>   = Pf G[2r, 2r+1, 2j, 2j+1]
+        tr(rho_A^2) = det(1 + G_A) / 2^l        (A = [0, l), G_A = G[0 .. 2l))
+    The Pfaffians of the nested blocks G[2r+1 .. 2j] follow from a single
+    elimination, and so do the determinants of the blocks [0, l), so that
+    chains of hundreds of sites take seconds.
+    -------------------------------------------------------------------
+    "save" writes the exact values, one row each, to "free_fermion/" in the
+    format of the QMC files: purity.dat, renyi2_ee.dat, zz_corr_2.dat,
+    xx_corr_2.dat and renyi2_profile.dat.
+*********************************************************************************/
+use std::fs;
+use crate::aux;
+use crate::ed::symmetric_eigen;
+use crate::error::{Error, Result};
+use crate::tfim::{Couplings, Lattice, LatticeKind};
+
+/// The subdirectory of the output written by [`MajoranaState::save`].
+pub const DIR_NAME: &str = "free_fermion";
+
+// pivots below this are treated as zero (the Pfaffians are then pivoted)
+const PIVOT_TOLERANCE: f64 = 1e-12;
+
+/// The Gaussian ground state of the chain, given by its covariance matrix
+pub struct MajoranaState {
+    /// The number of sites of the chain.
+    pub num_sites: usize,
+    /// The ground-state energy.
+    pub energy: f64,
+    covariance: Vec<Vec<f64>>,  // G, 2 num_sites x 2 num_sites
+}
+
+impl MajoranaState {
+    /// The ground state of a chain with the couplings `couplings`.
+    pub fn ground_state(lattice: &Lattice, couplings: &Couplings) -> Result<Self> {
+        if lattice.kind != LatticeKind::Chain {
+            return Err(Error::Lattice(format!("the free-fermion solution needs a chain, got {}", lattice)));
+        }
+        let num_sites = lattice.num_sites;
+        let n = 2 * num_sites;
+
+        // --------------------------------------------------------------
+        //  A_{2i, 2i+1} = - 2 h_i,  A_{2i+1, 2i+2} = - 2 J_b, and + 2 J_b
+        //  for the bond (L - 1, 0) closing a periodic chain
+        // --------------------------------------------------------------
+        let mut a = vec![vec![0.0; n]; n];
+        for (i, &h) in couplings.h.iter().enumerate() {
+            a[2 * i][2 * i + 1] = -2.0 * h;
+            a[2 * i + 1][2 * i] = 2.0 * h;
+        }
+        for (sites, &j) in lattice.b_sites.iter().zip(&couplings.j) {
+            let (k, l) = (2 * sites[0] + 1, (2 * sites[1]) % n);
+            let coupling = if sites[1] == (sites[0] + 1) % num_sites && sites[1] != 0 { -2.0 * j } else { 2.0 * j };
+            a[k][l] += coupling;
+            a[l][k] -= coupling;
+        }
+
+        // G = - A (A^T A)^{-1/2}, from the eigenpairs of A^T A = - A^2
+        let ata: Vec<Vec<f64>> = (0..n)
+            .map(|k| (0..n).map(|l| -(0..n).map(|m| a[k][m] * a[m][l]).sum::<f64>()).collect())
+            .collect();
+        let (values, vectors) = symmetric_eigen(ata);
+        let scale = values.iter().fold(0.0f64, |x, &y| x.max(y.abs()));
+        let mut inverse_root = vec![vec![0.0; n]; n];
+        for (&lambda, v) in values.iter().zip(&vectors) {
+            if lambda <= PIVOT_TOLERANCE * scale {
+                continue;
+            }
+            let w = 1.0 / lambda.sqrt();
+            for (row, &vk) in inverse_root.iter_mut().zip(v) {
+                row.iter_mut().zip(v).for_each(|(x, &vl)| *x += w * vk * vl);
+            }
+        }
+        let covariance: Vec<Vec<f64>> = (0..n)
+            .map(|k| (0..n).map(|l| -(0..n).map(|m| a[k][m] * inverse_root[m][l]).sum::<f64>()).collect())
+            .collect();
+
+        // E = (i/4) sum_kl A_kl <g_k g_l> = (1/4) sum_kl A_kl G_kl
+        let energy = a.iter().zip(&covariance).map(|(x, y)| x.iter().zip(y).map(|(p, q)| p * q).sum::<f64>()).sum::<f64>() / 4.0;
+        Ok(Self { num_sites, energy, covariance })
+    }
+
+    // the block of G with the rows and columns "indices"
+    fn block(&self, indices: &[usize]) -> Vec<Vec<f64>> {
+        indices.iter().map(|&k| indices.iter().map(|&l| self.covariance[k][l]).collect()).collect()
+    }
+
+    /// <Z_r Z_j> for j = 0, ..., num_sites - 1: the sites j > r are the
+    /// nested blocks G[2r+1 .. 2j], the sites j < r those of G[2j+1 .. 2r]
+    /// taken in reverse order, which flips the sign of an odd r - j
+    pub fn zz_corr(&self, r: usize) -> Vec<f64> {
+        let mut corr = vec![1.0; self.num_sites];
+        let right: Vec<usize> = (2 * r + 1..2 * self.num_sites - 1).collect();
+        for (k, pf) in leading_pfaffians(&self.block(&right)).into_iter().enumerate() {
+            corr[r + k + 1] = pf;
+        }
+        let left: Vec<usize> = (1..2 * r + 1).rev().collect();
+        for (k, pf) in leading_pfaffians(&self.block(&left)).into_iter().enumerate() {
+            corr[r - k - 1] = if k % 2 == 0 { -pf } else { pf };
+        }
+        corr
+    }
+
+    /// <X_r X_j> for j = 0, ..., num_sites - 1.
+    pub fn xx_corr(&self, r: usize) -> Vec<f64> {
+        (0..self.num_sites)
+            .map(|j| {
+                if j == r {
+                    return 1.0;
+                }
+                let (p, q) = (r.min(j), r.max(j));
+                let g = self.block(&[2 * p, 2 * p + 1, 2 * q, 2 * q + 1]);
+                g[0][1] * g[2][3] - g[0][2] * g[1][3] + g[0][3] * g[1][2]
+            })
+            .collect()
+    }
+
+    /// tr(rho_A^2) of the blocks A = [0, l), l = 1, ..., num_sites, from
+    /// the leading minors of 1 + G (by elimination without pivoting, as
+    /// the symmetric part of 1 + G is the identity), summed as logarithms
+    /// for long chains
+    pub fn block_purities(&self) -> Vec<f64> {
+        let n = 2 * self.num_sites;
+        let mut m = self.covariance.clone();
+        (0..n).for_each(|k| m[k][k] += 1.0);
+        let mut purities: Vec<f64> = Vec::with_capacity(self.num_sites);
+        let mut ln_det = 0.0;
+        for k in 0..n {
+            let pivot = m[k][k];
+            ln_det += pivot.ln();
+            let (upper, lower) = m.split_at_mut(k + 1);
+            for row in lower.iter_mut() {
+                let factor = row[k] / pivot;
+                row[k + 1..].iter_mut().zip(&upper[k][k + 1..]).for_each(|(x, y)| *x -= factor * y);
+            }
+            if k % 2 == 1 {
+                // the block of l = purities.len() + 1 sites
+                let l = purities.len() + 1;
+                purities.push((ln_det - l as f64 * std::f64::consts::LN_2).exp());
+            }
+        }
+        purities
+    }
+
+    /// The exact values in the format of the QMC files, in "target_dir"
+    pub fn save(&self, ref_site: usize, target_dir: &str) -> Result<()> {
+        fs::create_dir_all(target_dir).map_err(Error::io(target_dir))?;
+        let square = |values: Vec<f64>| -> Vec<f64> { values.into_iter().map(|x| x * x).collect() };
+        let purities = self.block_purities();
+        let renyi2: Vec<f64> = purities.iter().map(|p| -p.ln()).collect();
+        let row = |values: &[f64]| format!("{}\n", aux::format_row(values));
+
+        aux::write_file(format!("{}/purity.dat", target_dir), row(&[purities[self.num_sites - 1]]))?;
+        aux::write_file(format!("{}/renyi2_ee.dat", target_dir), row(&[renyi2[self.num_sites / 2 - 1]]))?;
+        aux::write_file(format!("{}/zz_corr_2.dat", target_dir), row(&square(self.zz_corr(ref_site))))?;
+        aux::write_file(format!("{}/xx_corr_2.dat", target_dir), row(&square(self.xx_corr(ref_site))))?;
+        aux::write_file(format!("{}/renyi2_profile.dat", target_dir), row(&renyi2[..self.num_sites - 1]))
+    }
+}
+
+// ------------------------------------------------------------------
+//  Pf M[0 .. 2k) for k = 1, ..., n / 2 of an antisymmetric M: the
+//  elimination of the pairs (0, 1), (2, 3), ... multiplies the leading
+//  Pfaffian by the pivots M[2k][2k+1]; after a vanishing pivot the rest
+//  are computed one by one with pivoting
+// ------------------------------------------------------------------
+fn leading_pfaffians(m: &[Vec<f64>]) -> Vec<f64> {
+    let n = m.len() - m.len() % 2;
+    let mut pfaffians: Vec<f64> = Vec::with_capacity(n / 2);
+    let mut work: Vec<Vec<f64>> = m.to_vec();
+    let mut pf = 1.0;
+    for k in (0..n).step_by(2) {
+        let pivot = work[k][k + 1];
+        if pivot.abs() < PIVOT_TOLERANCE {
+            pfaffians.extend((k..n).step_by(2).map(|l| pfaffian(m[..l + 2].iter().map(|row| row[..l + 2].to_vec()).collect())));
+            break;
+        }
+        pf *= pivot;
+        pfaffians.push(pf);
+        for i in k + 2..n {
+            for j in k + 2..n {
+                work[i][j] -= (work[k][i] * work[k + 1][j] - work[k + 1][i] * work[k][j]) / pivot;
+            }
+        }
+    }
+    pfaffians
+}
+
+// the Pfaffian of an antisymmetric matrix, by elimination with pivoting
+fn pfaffian(mut m: Vec<Vec<f64>>) -> f64 {
+    let n = m.len();
+    let mut pf = 1.0;
+    for k in (0..n).step_by(2) {
+        // bring the largest M[k][l] to M[k][k+1], a swap of l and k + 1 flipping the sign
+        let l = (k + 1..n).max_by(|&a, &b| m[k][a].abs().total_cmp(&m[k][b].abs())).unwrap();
+        if l != k + 1 {
+            m.swap(l, k + 1);
+            m.iter_mut().for_each(|row| row.swap(l, k + 1));
+            pf = -pf;
+        }
+        let pivot = m[k][k + 1];
+        if pivot == 0.0 {
+            return 0.0;
+        }
+        pf *= pivot;
+        for i in k + 2..n {
+            for j in k + 2..n {
+                m[i][j] -= (m[k][i] * m[k + 1][j] - m[k + 1][i] * m[k][j]) / pivot;
+            }
+        }
+    }
+    pf
+}
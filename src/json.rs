@@ -0,0 +1,293 @@
+/*********************************************************************************
+    Minimal JSON values for the run manifests
+    -------------------------------------------------------------------
+    Objects keep the order of their keys, and non-finite numbers are
+    written as null (JSON has no NaN or infinity). Numbers are read back
+    as Int without a fraction or an exponent, as Num otherwise, so that
+    a written value parses to the same value.
+*********************************************************************************/
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn obj(entries: Vec<(&str, Json)>) -> Self {
+        Json::Obj(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    pub fn str(s: impl fmt::Display) -> Self {
+        Json::Str(s.to_string())
+    }
+
+    pub fn strs<T: fmt::Display>(items: &[T]) -> Self {
+        Json::Arr(items.iter().map(Json::str).collect())
+    }
+
+    pub fn opt<T, F: FnOnce(T) -> Json>(value: Option<T>, f: F) -> Self {
+        value.map_or(Json::Null, f)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser { chars: text.chars().collect(), pos: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    // the value of "key" in an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Obj(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(i) => write!(f, "{}", i),
+            Json::Num(x) if x.is_finite() => write!(f, "{:?}", x),
+            Json::Num(_) => write!(f, "null"),
+            Json::Str(s) => write_str(f, s),
+            Json::Arr(items) if items.is_empty() => write!(f, "[]"),
+            Json::Arr(items) => {
+                writeln!(f, "[")?;
+                for (k, item) in items.iter().enumerate() {
+                    write!(f, "{}", pad)?;
+                    item.write(f, indent + 1)?;
+                    writeln!(f, "{}", if k + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{}]", "  ".repeat(indent))
+            }
+            Json::Obj(entries) if entries.is_empty() => write!(f, "{{}}"),
+            Json::Obj(entries) => {
+                writeln!(f, "{{")?;
+                for (k, (key, value)) in entries.iter().enumerate() {
+                    write!(f, "{}", pad)?;
+                    write_str(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, indent + 1)?;
+                    writeln!(f, "{}", if k + 1 < entries.len() { "," } else { "" })?;
+                }
+                write!(f, "{}}}", "  ".repeat(indent))
+            }
+        }
+    }
+}
+
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+// pretty-printed with two spaces per level
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+// ----------------------------------------------------------------
+//  Recursive descent parser
+// ----------------------------------------------------------------
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, msg: &str) -> String {
+        format!("invalid JSON at character {}: {}", self.pos, msg)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        let end = self.pos + word.len();
+        if end <= self.chars.len() && self.chars[self.pos..end].iter().copied().eq(word.chars()) {
+            self.pos = end;
+            Ok(value)
+        } else {
+            Err(self.error("unknown literal"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => Ok(Json::Str(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut items: Vec<Json> = Vec::new();
+                if self.peek() == Some(']') {
+                    self.pos += 1;
+                    return Ok(Json::Arr(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(']')?;
+                Ok(Json::Arr(items))
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut entries: Vec<(String, Json)> = Vec::new();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(Json::Obj(entries));
+                }
+                loop {
+                    if self.peek() != Some('"') {
+                        return Err(self.error("expected a key"));
+                    }
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    entries.push((key, self.value()?));
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect('}')?;
+                Ok(Json::Obj(entries))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while self.pos < self.chars.len() && matches!(self.chars[self.pos], '-' | '+' | '.' | 'e' | 'E' | '0'..='9') {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        if text.contains(['.', 'e', 'E']) {
+            text.parse().map(Json::Num).map_err(|_| self.error("invalid number"))
+        } else {
+            text.parse().map(Json::Int).map_err(|_| self.error("invalid number"))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = *self.chars.get(self.pos).ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let e = *self.chars.get(self.pos).ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match e {
+                        '"' | '\\' | '/' => s.push(e),
+                        'n' => s.push('\n'),
+                        't' => s.push('\t'),
+                        'r' => s.push('\r'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self.chars.iter().skip(self.pos).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16).map_err(|_| self.error("invalid escape"))?;
+                            s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            self.pos += 4;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let value = Json::obj(vec![
+            ("null", Json::Null),
+            ("flags", Json::Arr(vec![Json::Bool(true), Json::Bool(false)])),
+            ("int", Json::Int(-42)),
+            ("nums", Json::Arr(vec![Json::Num(1.0), Json::Num(0.1), Json::Num(-2.5e-300), Json::Num(6.02e23)])),
+            ("str", Json::str("a \"quoted\" \\ path\nwith\ttabs and \u{1}")),
+            ("empty", Json::Obj(Vec::new())),
+            ("nested", Json::obj(vec![("list", Json::Arr(Vec::new())), ("opt", Json::opt(Some(3), Json::Int))])),
+        ]);
+        assert_eq!(Json::parse(&value.to_string()).unwrap(), value);
+        assert_eq!(value.get("int"), Some(&Json::Int(-42)));
+        assert_eq!(value.get("missing"), None);
+    }
+
+    #[test]
+    fn non_finite_numbers_are_null() {
+        let value = Json::Arr(vec![Json::Num(f64::NAN), Json::Num(f64::INFINITY), Json::Num(f64::NEG_INFINITY)]);
+        assert_eq!(Json::parse(&value.to_string()).unwrap(), Json::Arr(vec![Json::Null; 3]));
+    }
+
+    #[test]
+    fn parses_escapes_and_whitespace() {
+        let value = Json::parse(" { \"a\" : [ 1 , 2.0 ] , \"b\" : \"\\u00e9\\/\\r\" } ").unwrap();
+        assert_eq!(value, Json::obj(vec![
+            ("a", Json::Arr(vec![Json::Int(1), Json::Num(2.0)])),
+            ("b", Json::str("\u{e9}/\r")),
+        ]));
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        for text in ["", "nul", "[1, 2", "{\"a\" 1}", "{1: 2}", "\"open", "\"\\q\"", "1.2.3", "[1] 2", "NaN"] {
+            assert!(Json::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+/*************************************************************************************
+ *  SSE for TFIM (chains, ladders, 2D lattices) under Bell basis, GS and finite-T versions
+ *  Author: Yi-Ming Ding
+ ************************************************************************************/
+//! Bell-basis stochastic series expansion of the transverse-field Ising model
+//!     H = - sum_b J_b Z_i Z_j - sum_i h_i X_i
+//! sampling rho ⊗ rho (ground state projection or finite temperature) to
+//! measure the purities, Renyi-2 entropies and squared correlators.
+//!
+//! A single model is built, thermalized and sampled with [`tfim::TFIModel`]:
+//!
+//! ```
+//! use bell_qmc_tfim_1d::tfim::{Boundary, Couplings, Lattice, SimulationMode, TFIModel};
+//!
+//! let lattice = Lattice::chain(8, Boundary::Open)?;
+//! let couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+//! let mut model = TFIModel::new(lattice, 8.0, couplings, 2025, SimulationMode::GroundState)?;
+//! model.init()?;
+//! for _ in 0..1000 {
+//!     model.mc_thermalizing();
+//! }
+//! model.ini_measure();
+//! for _ in 0..1000 {
+//!     model.mc_sampling();
+//! }
+//! model.statisticize();
+//! println!("S2 of the half chain = {}", -model.partial_purity().ln());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The runs of the command line, with their output files, are
+//! [`driver::run`] (bins of one chain, with checkpoints), [`driver::run_chains`],
+//! [`driver::run_disorder_average`] and [`sweep::run_sweep`], all dispatched
+//! by [`run`] from a [`config::SimulationConfig`]. Small lattices are checked
+//! against the exact values of [`ed::ExactState`], long chains against the
+//! free-fermion ground state [`free_fermion::MajoranaState`].
+
+#![warn(missing_docs)]
+
+pub(crate) mod aux;
+pub(crate) mod mt19937;
+/// The model, its lattices, couplings and measurements, and the MC updates.
+pub mod tfim;
+/// The runs of bins with their output files: one chain, parallel chains
+/// and disorder averages.
+pub mod driver;
+/// Binning, jackknife and autocorrelation analyses of the bins.
+pub mod stats;
+pub(crate) mod checkpoint;
+/// The parameters of a run from a config file and the command line.
+pub mod config;
+/// The errors of a run.
+pub mod error;
+pub(crate) mod json;
+pub(crate) mod manifest;
+pub(crate) mod output;
+/// Sweeps of a parameter with warm starts.
+pub mod sweep;
+/// Exact diagonalization of small lattices.
+pub mod ed;
+/// The exact ground state of the chain from free Majorana fermions.
+pub mod free_fermion;
+use std::time::Instant;
+use config::SimulationConfig;
+use error::Result;
+
+/// A run of the command line: prepare the output directory, write the
+/// manifest "run.json", then run the disorder average, the sweep, the
+/// parallel chains or the single chain of `para`.
+pub fn run(para: &SimulationConfig) -> Result<()> {
+    let driver::Schedule { num_thm, num_stat, num_bins } = para.schedule;
+    let (lattice, seed) = (&para.lattice, para.seed);
+    let mut info = manifest::RunInfo::start();
+    info.history = output::prepare(para)?;
+
+    // ------------------------------------------------------------------
+    //  Disorder average over "realizations" random couplings
+    // ------------------------------------------------------------------
+    if let Some(disorder_average) = para.disorder_average().filter(|_| para.num_realizations > 0) {
+        aux::print_horizontal_line(77, "-");
+        println!("■ Bell-QMC for TFIM on {lattice} ({}), disorder average", para.mode.describe());
+        println!("■ l = {}, beta = {}, J = {}, h = {}, disorder = {}", lattice.lx, para.beta, para.j, para.h, disorder_average.disorder);
+        println!("■ num_thm = {num_thm}, num_stat = {num_stat}, num_bins = {num_bins}, seed = {seed}");
+        println!("■ num_realizations = {}, disorder_seed = {}", para.num_realizations, para.disorder_seed);
+        manifest::write(para, &info)?;
+        let summary = driver::run_disorder_average(para, &disorder_average)?;
+        info.finish(&summary);
+        return manifest::write(para, &info);
+    }
+
+    // ------------------------------------------------------------------
+    //  Sweep of a parameter with warm starts
+    // ------------------------------------------------------------------
+    if let Some(sweep) = para.sweep.as_ref() {
+        aux::print_horizontal_line(77, "-");
+        println!("■ Bell-QMC for TFIM on {lattice} ({}), sweep", para.mode.describe());
+        println!("■ l = {}, beta = {}, J = {}, h = {}, sweep {}", lattice.lx, para.beta, para.j, para.h, sweep);
+        println!("■ num_thm = {num_thm}, num_stat = {num_stat}, num_bins = {num_bins}, seed = {seed}");
+        println!("■ num_thm_warm = {}", para.num_thm_warm.unwrap_or(num_thm));
+        manifest::write(para, &info)?;
+        let summary = sweep::run_sweep(para, sweep)?;
+        info.finish(&summary);
+        return manifest::write(para, &info);
+    }
+    let couplings = para.couplings()?;
+
+    // ===============================================================
+    //  Report the environment
+    // ================================================================
+    aux::print_horizontal_line(77, "-");
+    println!("■ Bell-QMC for TFIM on {lattice} ({})", para.mode.describe());
+    println!("■ l = {}, beta = {}, J = {}, h = {}", lattice.lx, para.beta, para.j, para.h);
+    println!("■ num_thm = {num_thm}, num_stat = {num_stat}, num_bins = {num_bins}, seed = {seed}");
+    if para.num_chains > 1 {
+        println!("■ num_chains = {} (in parallel)", para.num_chains);
+    }
+    for region in para.regions.iter() {
+        println!("■ region {region}");
+    }
+    if !couplings.is_uniform() {
+        println!("■ site- and bond-dependent couplings: sum(J_b) = {:.6}, sum(h_i) = {:.6}",
+                 couplings.total_j(), couplings.total_h());
+    }
+
+    // the exact ground state of the free fermions, in the format of the QMC files
+    if para.free_fermion {
+        let state = free_fermion::MajoranaState::ground_state(lattice, &couplings)?;
+        let target_dir = format!("{}/{}", para.output, free_fermion::DIR_NAME);
+        state.save(para.ref_site, &target_dir)?;
+        println!("■ free-fermion ground state: energy = {:.10}, written to \"{}\"", state.energy, target_dir);
+    }
+
+    // ===============================================================
+    //  Monte Carlo simulations
+    // ===============================================================
+    if para.num_chains > 1 {
+        // chain "c" runs with the seed derive_seed(seed, c)
+        let mut chains: Vec<(u32, tfim::TFIModel)> = (0..para.num_chains)
+            .map(|c| {
+                let chain_seed = aux::derive_seed(seed, c as u32);
+                Ok((chain_seed, para.build_model(couplings.clone(), chain_seed)?))
+            })
+            .collect::<Result<_>>()?;
+        couplings.save(&format!("{}/couplings.dat", para.output))?;
+        manifest::write(para, &info)?;
+        let summary = driver::run_chains(&mut chains, para.schedule, &para.output, para.max_runtime)?;
+        info.finish(&summary);
+        return manifest::write(para, &info);
+    }
+    let mut model = para.build_model(couplings.clone(), seed)?;
+    couplings.save(&format!("{}/couplings.dat", para.output))?;
+    let control = driver::Control {
+        checkpoint_path: para.checkpoint.as_deref(),
+        max_runtime: para.max_runtime,
+        stop_file: true,
+        stop_dir: None,
+        stop_flag: None,
+        quiet: false,
+    };
+    manifest::write(para, &info)?;
+    let outcome = driver::run(&mut model, para.schedule, &para.output, control)?;
+
+    // the exact values of a small lattice, once all bins are done
+    if para.exact && outcome.records.len() == num_bins {
+        let exact = ed::ExactState::new(lattice, &couplings, para.beta, para.mode)?;
+        ed::compare(&exact, &model, &outcome.records, &para.output)?;
+    }
+
+    let mut summary = driver::RunSummary::default();
+    summary.add(&model, &outcome, num_bins);
+    info.finish(&summary);
+    manifest::write(para, &info)
+}
+
+/// Print a horizontal line and the runtime since `start_time`, which closes
+/// the output of the command line.
+pub fn report_runtime(start_time: Instant) {
+    aux::print_horizontal_line(77, "-");
+    aux::report_time(start_time);
+}
@@ -0,0 +1,264 @@
+/*********************************************************************************
+    The manifest "run.json" of an output directory
+    -------------------------------------------------------------------
+    Written when a run starts (status "running") and again when it ends
+    ("completed", or "stopped" with a checkpoint to resume from):
+        program, version    the crate and its version
+        parameters          the parameters of SimulationConfig, with the
+                            effective beta_sse = 2 beta of the doubled SSE
+        schedule            num_thm, num_stat, num_bins, and num_thm_warm of
+                            the warm-started points of a sweep
+        results             bins done, thermalization and sampling steps,
+                            the final cut-off m and the average expansion
+                            order n over the sampled steps (summed over the
+                            points of a sweep, the realizations or the
+                            chains, with the largest m)
+        start_time, end_time, runtime_seconds   (UTC, ISO 8601)
+        outputs             every output file with the meaning of its columns
+        history             the earlier runs appended to (see output.rs)
+*********************************************************************************/
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::aux;
+use crate::config::SimulationConfig;
+use crate::driver::RunSummary;
+use crate::error::Result;
+use crate::free_fermion;
+use crate::json::Json;
+use crate::tfim::SimulationMode;
+
+pub const FILE_NAME: &str = "run.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Completed,
+    Stopped,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Running => "running",
+            Status::Completed => "completed",
+            Status::Stopped => "stopped",
+        }
+    }
+}
+
+// the progress of a run, as far as the manifest is concerned
+#[derive(Clone, Debug)]
+pub struct RunInfo {
+    pub status: Status,
+    pub start_time: SystemTime,
+    pub end_time: Option<SystemTime>,
+    pub num_thm_done: usize,
+    pub num_bins_done: usize,
+    pub final_m: Option<usize>,
+    pub average_n: Option<f64>,
+    pub history: Vec<Json>,
+}
+
+impl RunInfo {
+    pub fn start() -> Self {
+        Self {
+            status: Status::Running,
+            start_time: SystemTime::now(),
+            end_time: None,
+            num_thm_done: 0,
+            num_bins_done: 0,
+            final_m: None,
+            average_n: None,
+            history: Vec::new(),
+        }
+    }
+
+    // the end of the run, with what its runs have done
+    pub fn finish(&mut self, summary: &RunSummary) {
+        self.status = if summary.stopped { Status::Stopped } else { Status::Completed };
+        self.end_time = Some(SystemTime::now());
+        self.num_thm_done = summary.num_thm_done;
+        self.num_bins_done = summary.num_bins_done;
+        self.final_m = summary.final_m;
+        self.average_n = summary.average_n();
+    }
+}
+
+// ==========================================================================
+//  The parameters, also compared to decide whether a run may append
+// ==========================================================================
+pub fn parameters(para: &SimulationConfig) -> Json {
+    let lattice = &para.lattice;
+    Json::obj(vec![
+        ("lattice", Json::str(lattice.kind)),
+        ("l", Json::Int(lattice.lx as i64)),
+        ("ly", Json::Int(lattice.ly as i64)),
+        ("num_sites", Json::Int(lattice.num_sites as i64)),
+        ("bc", Json::str(lattice.boundary)),
+        ("beta", Json::Num(para.beta)),
+        ("beta_sse", Json::Num(2.0 * para.beta)),
+        ("j", Json::Num(para.j)),
+        ("h", Json::Num(para.h)),
+        ("mode", Json::str(para.mode)),
+        ("seed", Json::Int(para.seed as i64)),
+        ("couplings", Json::opt(para.couplings_file.as_ref(), Json::str)),
+        ("disorder", Json::opt(para.disorder, Json::str)),
+        ("disorder_seed", Json::Int(para.disorder_seed as i64)),
+        ("realizations", Json::Int(para.num_realizations as i64)),
+        ("chains", Json::Int(para.num_chains as i64)),
+        ("sweep", Json::opt(para.sweep.as_ref(), Json::str)),
+        ("regions", Json::strs(&para.regions)),
+        ("ref_site", Json::Int(para.ref_site as i64)),
+        ("corr", Json::strs(&para.pauli_pairs)),
+        ("corr_matrix", Json::Bool(para.corr_matrix)),
+        ("pauli_strings", Json::strs(&para.pauli_strings)),
+        ("exact", Json::Bool(para.exact)),
+        ("free_fermion", Json::Bool(para.free_fermion)),
+    ])
+}
+
+pub fn schedule(para: &SimulationConfig) -> Json {
+    let num_thm_warm = para.sweep.as_ref().map(|_| para.num_thm_warm.unwrap_or(para.schedule.num_thm));
+    Json::obj(vec![
+        ("num_thm", Json::Int(para.schedule.num_thm as i64)),
+        ("num_stat", Json::Int(para.schedule.num_stat as i64)),
+        ("num_bins", Json::Int(para.schedule.num_bins as i64)),
+        ("num_thm_warm", Json::opt(num_thm_warm, |n| Json::Int(n as i64))),
+    ])
+}
+
+// ==========================================================================
+//  The output files of a run and the meaning of their columns
+// ==========================================================================
+pub fn output_files(para: &SimulationConfig) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = Vec::new();
+    let mut add = |file: &str, columns: &str| files.push((file.to_string(), columns.to_string()));
+
+    add(FILE_NAME, "this manifest");
+    if let Some(sweep) = para.sweep.as_ref() {
+        let mut columns = format!("{}, warm start (1 or 0), average n, then the mean and error of purity, renyi2_ee, zz_corr_2_avg, xx_corr_2_avg", sweep.parameter);
+        if para.mode == SimulationMode::FiniteTemperature {
+            columns.push_str(", mutual_info2");
+        }
+        for region in para.regions.iter() {
+            columns.push_str(&format!(", renyi2_{}", region.name));
+        }
+        add("sweep.dat", &format!("{}, one row per point", columns));
+        add("point_<k>/", "the files of a single run for point k of the sweep (couplings.dat, purity.dat, checkpoint.bin, ...)");
+        return files;
+    }
+    if para.num_realizations > 0 {
+        add("realizations.dat", "realization r, disorder seed, seed, purity, S2 of the half system");
+        add("disorder_average.dat", "observable, mean over the realizations, sample-to-sample error");
+        add("disorder_zz_corr_2.dat", "means (first row) and errors (second row) of tr(rho Z_r Z_j)^2, j = 0, ..., L - 1");
+        add("disorder_xx_corr_2.dat", "means (first row) and errors (second row) of tr(rho X_r X_j)^2, j = 0, ..., L - 1");
+        add("disorder_renyi2_profile.dat", "means (first row) and errors (second row) of S2([0, l)), l = 1, ..., L - 1");
+        for pair in para.pauli_pairs.iter() {
+            add(&format!("disorder_{}.dat", pair.file_stem()), &format!("means and errors of tr(rho {}_r {}_j)^2", pair.0, pair.1));
+        }
+        add("realization_<r>/", "the files of a single run for realization r (couplings.dat, purity.dat, checkpoint.bin, ...)");
+        return files;
+    }
+
+    if para.free_fermion {
+        add(&format!("{}/", free_fermion::DIR_NAME), "exact free-fermion ground state in the format of the QMC files, one row each: purity.dat, renyi2_ee.dat, zz_corr_2.dat, xx_corr_2.dat, renyi2_profile.dat");
+    }
+    if para.num_chains > 1 {
+        add("chains.dat", "chain c, seed, num_bins, average n, purity, S2 of the half system");
+        add("convergence.dat", "observable, num_chains, mean, cross-chain error, Gelman-Rubin R-hat");
+        add("summary.dat", "observable, num_bins, mean, error, rebinned error, over the bins of all chains");
+        add("rebinning.dat", "observable, level, bin size, num_bins, error, over the bins of all chains");
+        add("jackknife.dat", "derived observable, estimate, bias-corrected estimate, error, over the bins of all chains");
+        add("chain_<c>/", "the files of a single run for chain c (couplings.dat, purity.dat, checkpoint.bin, ...)");
+        return files;
+    }
+
+    let per_bin = "one row per bin";
+    add("couplings.dat", "kind (J or h), bond or site index, value");
+    add("mode.dat", "key, value: mode, beta of each copy, beta_sse = 2 beta");
+    add("purity.dat", &format!("tr(rho^2) of the whole system, {}", per_bin));
+    add("renyi2_ee.dat", &format!("S2 = -ln tr(rho_A^2) of the half system A = [0, L/2), {}", per_bin));
+    add("zz_corr_2.dat", &format!("tr(rho Z_r Z_j)^2 for j = 0, ..., L - 1 and r = ref_site, {}", per_bin));
+    add("xx_corr_2.dat", &format!("tr(rho X_r X_j)^2 for j = 0, ..., L - 1 and r = ref_site, {}", per_bin));
+    add("renyi2_profile.dat", &format!("S2([0, l)) for l = 1, ..., L - 1, {}", per_bin));
+    if para.mode == SimulationMode::FiniteTemperature {
+        add("mutual_info2.dat", &format!("I2(A:B) = S2(A) + S2(B) - S2(AB) of the two halves, {}", per_bin));
+    }
+    if !para.regions.is_empty() {
+        add("regions.dat", "column, region name, sites");
+        add("renyi2_regions.dat", &format!("S2 of each region in the order of regions.dat, {}", per_bin));
+    }
+    for pair in para.pauli_pairs.iter() {
+        add(&format!("{}.dat", pair.file_stem()), &format!("tr(rho {}_r {}_j)^2 for j = 0, ..., L - 1, {}", pair.0, pair.1, per_bin));
+    }
+    if !para.pauli_strings.is_empty() {
+        add("pauli_strings.dat", "column, Pauli string");
+        add("pauli_strings_2.dat", &format!("tr(rho P)^2 of each Pauli string in the order of pauli_strings.dat, {}", per_bin));
+        add("pauli_strings_estimate.dat", "Pauli string, mean, error over the bins");
+    }
+    if para.corr_matrix {
+        for p in ["zz", "xx"] {
+            add(&format!("{}_corr_2_dist.dat", p), &format!("translation average C(d), d = 0, ..., L - 1, {}", per_bin));
+            add(&format!("{}_corr_2_matrix.dat", p), "L x L matrix of the squared correlators averaged over the bins, one row per site");
+            add(&format!("{}_corr_2_matrix_err.dat", p), "errors of the matrix, one row per site");
+        }
+    }
+    add("summary.dat", "observable, num_bins, mean, error, rebinned error");
+    add("rebinning.dat", "observable, level, bin size, num_bins, error");
+    add("jackknife.dat", "derived observable, estimate, bias-corrected estimate, error");
+    add("autocorrelation.dat", "observable, num_samples, tau_int, error, block size, for the purities (purity_<name> of each region as well), n, the site-averaged correlators zz_corr_2_avg, xx_corr_2_avg (and pq_corr_2_avg of each further correlator) and pauli_<P> of each Pauli string");
+    if para.exact {
+        add("exact.dat", "observable, exact value, mean, error, deviation (mean - exact) / error");
+    }
+    if let Some(path) = para.checkpoint.as_ref() {
+        add(path, "binary checkpoint to resume from (see checkpoint.rs)");
+    }
+    files
+}
+
+// ==========================================================================
+//  Write "run.json" to the output directory
+// ==========================================================================
+pub fn write(para: &SimulationConfig, info: &RunInfo) -> Result<()> {
+    let runtime = info.end_time.map(|end| end.duration_since(info.start_time).unwrap_or_default().as_secs_f64());
+    let outputs: Vec<Json> = output_files(para)
+        .into_iter()
+        .map(|(file, columns)| Json::obj(vec![("file", Json::Str(file)), ("columns", Json::Str(columns))]))
+        .collect();
+
+    let manifest = Json::obj(vec![
+        ("program", Json::str(env!("CARGO_PKG_NAME"))),
+        ("version", Json::str(env!("CARGO_PKG_VERSION"))),
+        ("status", Json::str(info.status.as_str())),
+        ("parameters", parameters(para)),
+        ("schedule", schedule(para)),
+        ("results", Json::obj(vec![
+            ("num_bins_done", Json::Int(info.num_bins_done as i64)),
+            ("thermalization_steps", Json::Int(info.num_thm_done as i64)),
+            ("sampling_steps", Json::Int((info.num_bins_done * para.schedule.num_stat) as i64)),
+            ("final_m", Json::opt(info.final_m, |m| Json::Int(m as i64))),
+            ("average_n", Json::opt(info.average_n, Json::Num)),
+        ])),
+        ("start_time", Json::Str(format_utc(info.start_time))),
+        ("end_time", Json::opt(info.end_time, |end| Json::Str(format_utc(end)))),
+        ("runtime_seconds", Json::opt(runtime, Json::Num)),
+        ("outputs", Json::Arr(outputs)),
+        ("history", Json::Arr(info.history.clone())),
+    ]);
+    aux::write_file(format!("{}/{}", para.output, FILE_NAME), format!("{}\n", manifest))
+}
+
+// e.g. "2025-03-12T08:30:00Z", from the days since 1970-01-01 (proleptic Gregorian)
+pub fn format_utc(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
@@ -0,0 +1,243 @@
+/*********************************************************************************
+    The output directory of a run
+    -------------------------------------------------------------------
+    The directory is created if needed. If it already holds results
+    ("run.json", ".dat" files, "realization_<r>/", "point_<k>/",
+    "chain_<c>/" or "free_fermion/" directories), the run
+    proceeds according to "on-existing":
+        refuse      stop with an error (the default)
+        overwrite   remove those results first (other files are kept)
+        append      append the new bins to the ".dat" files, only if the
+                    stored manifest has the same parameters and "num_stat",
+                    and a different seed (the same seed would repeat the
+                    stored bins); the stored runs go to "history"
+                    (the analysis files, "summary.dat" etc., cover the
+                    bins of the latest run only)
+    A run resuming from an existing checkpoint needs no mode, but the
+    stored manifest must match it, seed included. A sweep, a disorder
+    average or parallel chains stopped early (stored status "stopped")
+    resume from the checkpoints of their points, realizations or chains
+    when run again with the same parameters, seed included, unless
+    "on-existing" is "overwrite".
+*********************************************************************************/
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use crate::config::SimulationConfig;
+use crate::error::{Error, Result};
+use crate::free_fermion;
+use crate::json::Json;
+use crate::manifest;
+
+/// What to do with an output directory that already holds results
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnExisting {
+    /// Stop with an error.
+    Refuse,
+    /// Remove the old results first.
+    Overwrite,
+    /// Append the new bins to the old ones.
+    Append,
+}
+
+impl FromStr for OnExisting {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "refuse" => Ok(OnExisting::Refuse),
+            "overwrite" => Ok(OnExisting::Overwrite),
+            "append" => Ok(OnExisting::Append),
+            _ => Err(format!("unknown mode \"{}\" (expected \"refuse\", \"overwrite\" or \"append\")", s)),
+        }
+    }
+}
+
+impl fmt::Display for OnExisting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnExisting::Refuse => write!(f, "refuse"),
+            OnExisting::Overwrite => write!(f, "overwrite"),
+            OnExisting::Append => write!(f, "append"),
+        }
+    }
+}
+
+// the entries of "dir" that are results of a run
+fn results_in(dir: &Path) -> Result<Vec<String>> {
+    let mut results: Vec<String> = Vec::new();
+    for entry in fs::read_dir(dir).map_err(Error::io(&dir.to_string_lossy()))? {
+        let entry = entry.map_err(Error::io(&dir.to_string_lossy()))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_subrun = ["realization_", "point_", "chain_"].iter().any(|prefix| {
+            name.strip_prefix(prefix).is_some_and(|r| !r.is_empty() && r.chars().all(|c| c.is_ascii_digit()))
+        });
+        let is_result_dir = (is_subrun || name == free_fermion::DIR_NAME) && entry.path().is_dir();
+        if name == manifest::FILE_NAME || name.ends_with(".dat") || is_result_dir {
+            results.push(name);
+        }
+    }
+    results.sort();
+    Ok(results)
+}
+
+fn read_manifest(dir: &Path) -> Result<Option<Json>> {
+    let path = dir.join(manifest::FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let path_str = path.to_string_lossy().to_string();
+    let text = fs::read_to_string(&path).map_err(Error::io(&path_str))?;
+    let stored = Json::parse(&text).map_err(|e| Error::Config(format!("\"{}\": {}", path_str, e)))?;
+    Ok(Some(stored))
+}
+
+// the parameters that must agree for bins to be appended, without the seeds
+// (the disorder seed is kept when there is disorder, as it fixes the couplings)
+fn comparable(parameters: &Json, num_stat: Option<&Json>) -> Json {
+    let has_disorder = parameters.get("disorder").is_some_and(|d| *d != Json::Null);
+    let mut entries: Vec<(String, Json)> = match parameters {
+        Json::Obj(entries) => entries
+            .iter()
+            .filter(|(key, _)| key != "seed" && (has_disorder || key != "disorder_seed"))
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    };
+    entries.push(("num_stat".to_string(), num_stat.cloned().unwrap_or(Json::Null)));
+    Json::Obj(entries)
+}
+
+// the first differing parameter, if any
+fn difference(stored: &Json, current: &Json) -> Option<String> {
+    let (Json::Obj(stored), Json::Obj(current)) = (stored, current) else {
+        return Some("no parameters".to_string());
+    };
+    for (key, value) in current.iter() {
+        match stored.iter().find(|(k, _)| k == key) {
+            Some((_, stored_value)) if stored_value == value => {}
+            Some((_, stored_value)) => {
+                return Some(format!("\"{}\" is {} here but {} in the stored run", key, compact(value), compact(stored_value)))
+            }
+            None => return Some(format!("\"{}\" is missing in the stored run", key)),
+        }
+    }
+    None
+}
+
+fn compact(value: &Json) -> String {
+    value.to_string().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// ==========================================================================
+//  Create or check the output directory, and return the history of the
+//  stored runs that this one appends to (empty otherwise)
+// ==========================================================================
+pub fn prepare(para: &SimulationConfig) -> Result<Vec<Json>> {
+    let dir = Path::new(&para.output);
+    fs::create_dir_all(dir).map_err(Error::io(&para.output))?;
+    let results = results_in(dir)?;
+    if results.is_empty() {
+        return Ok(Vec::new());
+    }
+    let stored = read_manifest(dir)?;
+    let current_parameters = manifest::parameters(para);
+    let history = |stored: &Json| -> Vec<Json> {
+        let mut history: Vec<Json> = match stored.get("history") {
+            Some(Json::Arr(runs)) => runs.clone(),
+            _ => Vec::new(),
+        };
+        let mut run: Vec<(String, Json)> = Vec::new();
+        for key in ["start_time", "end_time", "status"] {
+            run.push((key.to_string(), stored.get(key).cloned().unwrap_or(Json::Null)));
+        }
+        let seed = stored.get("parameters").and_then(|p| p.get("seed")).cloned().unwrap_or(Json::Null);
+        run.push(("seed".to_string(), seed));
+        run.push(("results".to_string(), stored.get("results").cloned().unwrap_or(Json::Null)));
+        history.push(Json::Obj(run));
+        history
+    };
+
+    // -----------------------------------------------------
+    //  Resuming from a checkpoint continues the same run
+    // -----------------------------------------------------
+    if para.checkpoint.as_ref().is_some_and(|path| Path::new(path).exists()) {
+        if let Some(stored) = stored.as_ref() {
+            let stored_parameters = stored.get("parameters").cloned().unwrap_or(Json::Null);
+            if let Some(diff) = difference(&stored_parameters, &current_parameters) {
+                return Err(Error::Config(format!("cannot resume into \"{}\": {}", para.output, diff)));
+            }
+            if let Some(Json::Arr(runs)) = stored.get("history") {
+                return Ok(runs.clone());
+            }
+        }
+        return Ok(Vec::new());
+    }
+
+    // -----------------------------------------------------------------
+    //  A stopped sweep, disorder average or set of parallel chains, run
+    //  again with the same parameters, resumes from the checkpoints of
+    //  its runs
+    // -----------------------------------------------------------------
+    let has_subruns = para.sweep.is_some() || para.num_realizations > 0 || para.num_chains > 1;
+    if has_subruns
+        && para.on_existing != OnExisting::Overwrite
+        && let Some(stored) = stored.as_ref()
+        && stored.get("status") == Some(&Json::str(manifest::Status::Stopped.as_str()))
+        && difference(&stored.get("parameters").cloned().unwrap_or(Json::Null), &current_parameters).is_none()
+    {
+        println!("■ resuming the stopped run in \"{}\"", para.output);
+        return Ok(match stored.get("history") {
+            Some(Json::Arr(runs)) => runs.clone(),
+            _ => Vec::new(),
+        });
+    }
+
+    match para.on_existing {
+        OnExisting::Refuse => Err(Error::Config(format!(
+            "output directory \"{}\" already holds results ({}); set \"on-existing\" to \"overwrite\" or \"append\"",
+            para.output,
+            results.join(", ")
+        ))),
+        OnExisting::Overwrite => {
+            for name in results.iter() {
+                let path = dir.join(name);
+                let path_str = path.to_string_lossy().to_string();
+                if path.is_dir() {
+                    fs::remove_dir_all(&path).map_err(Error::io(&path_str))?;
+                } else {
+                    fs::remove_file(&path).map_err(Error::io(&path_str))?;
+                }
+            }
+            println!("■ removed the results in \"{}\" ({})", para.output, results.join(", "));
+            Ok(Vec::new())
+        }
+        OnExisting::Append => {
+            if para.num_realizations > 0 {
+                return Err(Error::Config("\"on-existing = append\" is not supported with \"realizations\"".to_string()));
+            }
+            let stored = stored.ok_or_else(|| {
+                Error::Config(format!("cannot append to \"{}\": no {} to compare the parameters with", para.output, manifest::FILE_NAME))
+            })?;
+            let stored_parameters = stored.get("parameters").cloned().unwrap_or(Json::Null);
+            let stored_num_stat = stored.get("schedule").and_then(|s| s.get("num_stat"));
+            let current_num_stat = Json::Int(para.schedule.num_stat as i64);
+            if let Some(diff) = difference(
+                &comparable(&stored_parameters, stored_num_stat),
+                &comparable(&current_parameters, Some(&current_num_stat)),
+            ) {
+                return Err(Error::Config(format!("cannot append to \"{}\": {}", para.output, diff)));
+            }
+            let runs = history(&stored);
+            if runs.iter().any(|run| run.get("seed") == current_parameters.get("seed")) {
+                return Err(Error::Config(format!(
+                    "cannot append to \"{}\": seed {} was already used there, and would repeat its bins",
+                    para.output, para.seed
+                )));
+            }
+            println!("■ appending to the {} stored run(s) in \"{}\"", runs.len(), para.output);
+            Ok(runs)
+        }
+    }
+}
@@ -0,0 +1,467 @@
+/*********************************************************************************
+    Binning analysis of the measured observables
+    -------------------------------------------------------------------
+    Every observable collects one value per bin. The summary reports
+        mean        the average over the bins
+        error       the standard error, taking the bins as independent
+        rebinned    the largest standard error of the log2 rebinning, where
+                    level k merges 2^k consecutive bins (levels with at
+                    least MIN_BINS bins only)
+    If "rebinned" is clearly larger than "error", the bins are still
+    correlated and "num_stat" should be increased.
+    -------------------------------------------------------------------
+    A nonlinear function f of bin averages (e.g. S2 = -ln(purity)) is
+    estimated by the jackknife: with the leave-one-out values
+        f_i = f(means without bin i),   f_J = mean of f_i
+    the bias-corrected estimate and the error are
+        n f(means) - (n - 1) f_J,   sqrt((n - 1) / n * sum_i (f_i - f_J)^2)
+    -------------------------------------------------------------------
+    For a time series x_t (one value per MC step), the integrated
+    autocorrelation time follows from the blocking analysis: with the
+    variance s_k^2 of the means of the blocks of B = 2^k steps,
+        tau_int(k) = B s_k^2 / (2 s_0^2) = tau_int - c / B + O(e^{-B / tau_int})
+    with c = sum_t t rho(t), so that 2 tau_int(k + 1) - tau_int(k) removes
+    the 1 / B term. The block size is the smallest B = 2^(k + 1) of the
+    level k + 1 the estimate is taken from with B >= SOKAL_C * tau_int
+    (Sokal's window) among the levels with at least MIN_BLOCKS blocks,
+    else the largest of them, and the error follows from the relative
+    errors sqrt(2 / (n_k - 1)) of s_k^2 for n_k blocks. Each level keeps
+    the running mean and variance of its block means and a half-filled
+    block, i.e. O(log2 N) numbers for N steps, and a bin of "num_stat"
+    steps holds about num_stat / (2 tau_int) independent samples.
+    -------------------------------------------------------------------
+    For K independent chains of n bins each, with the chain means m_k,
+    the within-chain variance W (the average of the bin variances of the
+    chains) and the between-chain variance B = n var(m_k), the R-hat of
+    Gelman and Rubin is
+        R = sqrt(((n - 1) / n W + B / n) / W)
+    which approaches 1 when the chains sample the same distribution
+    (R > 1.1 being the usual warning sign), and the cross-chain error of
+    the mean is the standard error of the m_k.
+*********************************************************************************/
+use std::collections::HashMap;
+use crate::aux;
+use crate::error::Result;
+
+const MIN_BINS: usize = 4;
+const MIN_BLOCKS: usize = 32;
+const SOKAL_C: f64 = 6.0;
+
+/// The bin averages of an observable.
+#[derive(Clone, Debug)]
+pub struct Observable {
+    /// The name, e.g. `purity` or `zz_corr_2[3]`.
+    pub name: String,
+    /// One value per bin, in order.
+    pub bins: Vec<f64>,
+}
+
+/// A level of the log2 rebinning.
+#[derive(Clone, Copy, Debug)]
+pub struct BinningLevel {
+    /// The number of original bins merged into one.
+    pub bin_size: usize,
+    /// The number of merged bins.
+    pub num_bins: usize,
+    /// The naive error of the mean from the merged bins.
+    pub error: f64,
+}
+
+/// The mean and the errors of an observable.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    /// The name of the observable.
+    pub name: String,
+    /// The number of bins.
+    pub num_bins: usize,
+    /// The mean over the bins.
+    pub mean: f64,
+    /// The naive error of the mean.
+    pub error: f64,
+    /// The error at the largest level with at least MIN_BINS bins.
+    pub rebinned_error: f64,
+    /// The levels of the rebinning.
+    pub levels: Vec<BinningLevel>,
+}
+
+/// The jackknife estimate of a function of observables.
+#[derive(Clone, Debug)]
+pub struct Derived {
+    /// The name of the derived observable, e.g. "renyi2_ee".
+    pub name: String,
+    /// The function of the means.
+    pub estimate: f64,
+    /// The estimate with the jackknife bias correction.
+    pub bias_corrected: f64,
+    /// The jackknife error.
+    pub error: f64,
+}
+
+/// The integrated autocorrelation time of a time series, in MC steps.
+#[derive(Clone, Debug)]
+pub struct Autocorrelation {
+    /// The name of the time series.
+    pub name: String,
+    /// The number of values of the series.
+    pub num_samples: usize,
+    /// The estimate of tau_int (0.5 for uncorrelated values).
+    pub tau_int: f64,
+    /// The block size of the estimate.
+    pub block_size: usize,
+    /// The statistical error of tau_int.
+    pub error: f64,
+}
+
+// ----------------------------------------------------------------
+//  Level k of the blocking analysis: the running mean and the sum
+//  of squared deviations (Welford) of the means of the blocks of
+//  2^k values, and the first half of the next block
+// ----------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct BlockLevel {
+    pub(crate) num_blocks: usize,
+    pub(crate) mean: f64,
+    pub(crate) m2: f64,
+    pub(crate) pending: Option<f64>,
+}
+
+impl BlockLevel {
+    fn variance(&self) -> f64 {
+        self.m2 / (self.num_blocks - 1) as f64
+    }
+}
+
+/// A time series of one value per MC step, kept as the levels of its
+/// blocking analysis only, i.e. O(log2 N) numbers for N values.
+#[derive(Clone, Debug)]
+pub struct TimeSeries {
+    pub(crate) name: String,
+    pub(crate) levels: Vec<BlockLevel>,
+}
+
+impl Observable {
+    /// The levels 0, 1, 2, ... of the log2 rebinning, dropping the incomplete last bin.
+    pub fn rebinning(&self) -> Vec<BinningLevel> {
+        let mut levels: Vec<BinningLevel> = Vec::new();
+        let mut bins: Vec<f64> = self.bins.clone();
+        let mut bin_size: usize = 1;
+        while bins.len() >= 2 {
+            let (_, error) = aux::mean_and_error(&bins);
+            levels.push(BinningLevel { bin_size, num_bins: bins.len(), error });
+            bins = bins.chunks_exact(2).map(|pair| 0.5 * (pair[0] + pair[1])).collect();
+            bin_size *= 2;
+        }
+        levels
+    }
+
+    /// The mean and the errors of the bins.
+    pub fn summarize(&self) -> Summary {
+        let (mean, error) = aux::mean_and_error(&self.bins);
+        let levels = self.rebinning();
+        let rebinned_error = levels
+            .iter()
+            .filter(|level| level.num_bins >= MIN_BINS)
+            .map(|level| level.error)
+            .fold(error, f64::max);
+
+        Summary { name: self.name.clone(), num_bins: self.bins.len(), mean, error, rebinned_error, levels }
+    }
+}
+
+impl TimeSeries {
+    /// An empty time series.
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), levels: Vec::new() }
+    }
+
+    /// The number of values pushed.
+    pub fn num_samples(&self) -> usize {
+        self.levels.first().map_or(0, |level| level.num_blocks)
+    }
+
+    /// Append the value of the next MC step.
+    pub fn push(&mut self, x: f64) {
+        let mut value = x;
+        for k in 0.. {
+            if k == self.levels.len() {
+                self.levels.push(BlockLevel::default());
+            }
+            let level = &mut self.levels[k];
+            level.num_blocks += 1;
+            let delta = value - level.mean;
+            level.mean += delta / level.num_blocks as f64;
+            level.m2 += delta * (value - level.mean);
+            match level.pending.take() {
+                Some(first) => value = 0.5 * (first + value),
+                None => {
+                    level.pending = Some(value);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// The integrated autocorrelation time of the values so far.
+    pub fn autocorrelation(&self) -> Autocorrelation {
+        let n = self.num_samples();
+        let mut tau_int: f64 = 0.5;
+        let mut block_size: usize = 1;
+        let mut error = f64::NAN;
+
+        // a constant series (e.g. the purity of a ground state) is uncorrelated
+        let s0 = if n >= 2 { self.levels[0].variance() } else { 0.0 };
+        if s0 > 0.0 {
+            // tau_int(k) and its error, for the levels with enough blocks
+            let taus: Vec<(f64, f64)> = self.levels
+                .iter()
+                .take_while(|level| level.num_blocks >= MIN_BLOCKS)
+                .enumerate()
+                .map(|(k, level)| {
+                    let tau = (1usize << k) as f64 * level.variance() / (2.0 * s0);
+                    (tau, tau * (2.0 / (level.num_blocks - 1) as f64).sqrt())
+                })
+                .collect();
+            for (k, pair) in taus.windows(2).enumerate() {
+                let ((tau_k, error_k), (tau_k1, error_k1)) = (pair[0], pair[1]);
+                block_size = 1 << (k + 1);
+                tau_int = 2.0 * tau_k1 - tau_k;
+                error = (4.0 * error_k1 * error_k1 + error_k * error_k).sqrt();
+                if block_size as f64 >= SOKAL_C * tau_int {
+                    break;
+                }
+            }
+        } else if n >= 2 {
+            error = 0.0;
+        }
+        Autocorrelation { name: self.name.clone(), num_samples: n, tau_int, block_size, error }
+    }
+}
+
+/// Gelman-Rubin R-hat of chains with the same number (at least 2) of
+/// bins, NaN with less than two chains or a vanishing variance.
+pub fn gelman_rubin(chains: &[&[f64]]) -> f64 {
+    let k = chains.len();
+    let n = chains.first().map_or(0, |bins| bins.len());
+    if k < 2 || n < 2 || chains.iter().any(|bins| bins.len() != n) {
+        return f64::NAN;
+    }
+    let means: Vec<f64> = chains.iter().map(|bins| bins.iter().sum::<f64>() / n as f64).collect();
+    let grand_mean = means.iter().sum::<f64>() / k as f64;
+    let within = chains
+        .iter()
+        .zip(&means)
+        .map(|(bins, mean)| bins.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1) as f64)
+        .sum::<f64>() / k as f64;
+    let between = n as f64 * means.iter().map(|m| (m - grand_mean) * (m - grand_mean)).sum::<f64>() / (k - 1) as f64;
+    if within == 0.0 {
+        return f64::NAN;
+    }
+    let pooled = (n - 1) as f64 / n as f64 * within + between / n as f64;
+    (pooled / within).sqrt()
+}
+
+/// The observables of a run, kept in the order of their first bin, and
+/// the jackknife estimates of their functions.
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    pub(crate) observables: Vec<Observable>,
+    pub(crate) derived: Vec<Derived>,
+    index: HashMap<String, usize>,      // the position of each observable by name
+}
+
+impl Statistics {
+    /// No observables.
+    pub fn new() -> Self {
+        Self { observables: Vec::new(), derived: Vec::new(), index: HashMap::new() }
+    }
+
+    /// Append a bin to the observable `name`, created if needed.
+    pub fn add(&mut self, name: &str, value: f64) {
+        match self.index.get(name) {
+            Some(&i) => self.observables[i].bins.push(value),
+            None => self.insert(Observable { name: name.to_string(), bins: vec![value] }),
+        }
+    }
+
+    /// A new observable with its bins (e.g. from a checkpoint).
+    pub fn insert(&mut self, obs: Observable) {
+        self.index.insert(obs.name.clone(), self.observables.len());
+        self.observables.push(obs);
+    }
+
+    /// Append a bin to the observables `name[s]`, one per site.
+    pub fn add_row(&mut self, name: &str, values: &[f64]) {
+        for (s, &value) in values.iter().enumerate() {
+            self.add(&format!("{}[{}]", name, s), value);
+        }
+    }
+
+    /// The observables, in the order of their first bin.
+    pub fn observables(&self) -> &[Observable] {
+        &self.observables
+    }
+
+    /// The jackknife estimates kept by [`Statistics::add_derived`].
+    pub fn derived(&self) -> &[Derived] {
+        &self.derived
+    }
+
+    /// The observable `name`, if it has bins.
+    pub fn get(&self, name: &str) -> Option<&Observable> {
+        self.index.get(name).map(|&i| &self.observables[i])
+    }
+
+    /// Jackknife estimate of `f(<inputs[0]>, <inputs[1]>, ...)`, the inputs
+    /// being observables with the same number (at least 2) of bins.
+    pub fn jackknife<F: Fn(&[f64]) -> f64>(&self, name: &str, inputs: &[&str], f: F) -> Option<Derived> {
+        let observables: Vec<&Observable> = inputs.iter().map(|input| self.get(input)).collect::<Option<_>>()?;
+        let n = observables.first()?.bins.len();
+        if n < 2 || observables.iter().any(|obs| obs.bins.len() != n) {
+            return None;
+        }
+
+        let sums: Vec<f64> = observables.iter().map(|obs| obs.bins.iter().sum()).collect();
+        let means: Vec<f64> = sums.iter().map(|sum| sum / n as f64).collect();
+        let estimate = f(&means);
+
+        let leave_one_out: Vec<f64> = (0..n)
+            .map(|i| {
+                let means_i: Vec<f64> = observables
+                    .iter()
+                    .zip(&sums)
+                    .map(|(obs, sum)| (sum - obs.bins[i]) / (n - 1) as f64)
+                    .collect();
+                f(&means_i)
+            })
+            .collect();
+        let mean_jk = leave_one_out.iter().sum::<f64>() / n as f64;
+        let var_jk = leave_one_out.iter().map(|x| (x - mean_jk) * (x - mean_jk)).sum::<f64>();
+
+        Some(Derived {
+            name: name.to_string(),
+            estimate,
+            bias_corrected: n as f64 * estimate - (n - 1) as f64 * mean_jk,
+            error: ((n - 1) as f64 / n as f64 * var_jk).sqrt(),
+        })
+    }
+
+    /// Keep the jackknife estimate (if any) for the summary.
+    pub fn add_derived<F: Fn(&[f64]) -> f64>(&mut self, name: &str, inputs: &[&str], f: F) {
+        if let Some(derived) = self.jackknife(name, inputs, f) {
+            self.derived.push(derived);
+        }
+    }
+
+    /// The summaries of all observables, in order.
+    pub fn summarize(&self) -> Vec<Summary> {
+        self.observables.iter().map(|obs| obs.summarize()).collect()
+    }
+
+    /// Write the analysis to "target_dir":
+    ///     "summary.dat":    observable, num_bins, mean, error, rebinned error
+    ///     "rebinning.dat":  observable, level, bin size, num_bins, error
+    ///     "jackknife.dat":  derived observable, estimate, bias-corrected, error
+    pub fn save(&self, target_dir: &str) -> Result<()> {
+        let summaries = self.summarize();
+
+        let mut summary = String::from("# observable  num_bins  mean  error  rebinned_error\n");
+        let mut rebinning = String::from("# observable  level  bin_size  num_bins  error\n");
+        for s in summaries.iter() {
+            summary.push_str(&format!(
+                "{}\t{}\t{:<16.10}\t{:<16.10}\t{:<16.10}\n", s.name, s.num_bins, s.mean, s.error, s.rebinned_error
+            ));
+            for (k, level) in s.levels.iter().enumerate() {
+                rebinning.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{:<16.10}\n", s.name, k, level.bin_size, level.num_bins, level.error
+                ));
+            }
+        }
+
+        aux::write_file(format!("{}/summary.dat", target_dir), summary)?;
+        aux::write_file(format!("{}/rebinning.dat", target_dir), rebinning)?;
+
+        let mut jackknife = String::from("# observable  estimate  bias_corrected  error\n");
+        for d in self.derived.iter() {
+            jackknife.push_str(&format!(
+                "{}\t{:<16.10}\t{:<16.10}\t{:<16.10}\n", d.name, d.estimate, d.bias_corrected, d.error
+            ));
+        }
+        aux::write_file(format!("{}/jackknife.dat", target_dir), jackknife)
+    }
+
+    /// Print the scalar observables only, the site-resolved ones are in "summary.dat".
+    pub fn print_table(&self) {
+        println!("\t{:<24} {:>18} {:>14} {:>14}", "observable", "mean", "error", "rebinned");
+        for s in self.summarize().iter().filter(|s| !s.name.contains('[')) {
+            println!("\t{:<24} {:>18.10} {:>14.3e} {:>14.3e}", s.name, s.mean, s.error, s.rebinned_error);
+        }
+
+        if self.derived.iter().any(|d| !d.name.contains('[')) {
+            println!("\t{:<24} {:>18} {:>14}", "jackknife", "bias-corrected", "error");
+            for d in self.derived.iter().filter(|d| !d.name.contains('[')) {
+                println!("\t{:<24} {:>18.10} {:>14.3e}", d.name, d.bias_corrected, d.error);
+            }
+        }
+    }
+}
+
+/// Save "autocorrelation.dat" (observable, samples, tau_int, error, block
+/// size) of the time series, and print tau_int of each of them if `print`.
+pub fn save_autocorrelation(series: &[TimeSeries], target_dir: &str, num_stat: usize, print: bool) -> Result<()> {
+    let mut content = String::from("# observable  num_samples  tau_int  error  block_size\n");
+    if print {
+        println!("\t{:<24} {:>14} {:>12} {:>8} {:>18}", "observable", "tau_int", "error", "block", "independent / bin");
+    }
+    for a in series.iter().map(|s| s.autocorrelation()) {
+        content.push_str(&format!(
+            "{}\t{}\t{:<16.10}\t{:<16.10}\t{}\n", a.name, a.num_samples, a.tau_int, a.error, a.block_size
+        ));
+        if print {
+            println!(
+                "\t{:<24} {:>14.4} {:>12.2e} {:>8} {:>18.1}",
+                a.name, a.tau_int, a.error, a.block_size, num_stat as f64 / (2.0 * a.tau_int)
+            );
+        }
+    }
+    aux::write_file(format!("{}/autocorrelation.dat", target_dir), content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mt19937::MT19937;
+
+    // x_{t+1} = rho x_t + e_t has rho(t) = rho^t, i.e. tau_int = (1 + rho) / (2 (1 - rho))
+    fn ar1_tau(rho: f64, num_samples: usize) -> Autocorrelation {
+        let mut rng = MT19937::new(12345);
+        let mut uniform = move || (rng.next_u32() as f64 + 0.5) / 4294967296.0;
+        let mut series = TimeSeries::new("ar1");
+        let mut x = 0.0;
+        for _ in 0..num_samples {
+            x = rho * x + uniform() - 0.5;
+            series.push(x);
+        }
+        series.autocorrelation()
+    }
+
+    #[test]
+    fn blocking_recovers_ar1_tau() {
+        for rho in [0.0, 0.8, 0.95] {
+            let a = ar1_tau(rho, 1 << 20);
+            let exact = (1.0 + rho) / (2.0 * (1.0 - rho));
+            assert_eq!(a.num_samples, 1 << 20);
+            assert!((a.tau_int - exact).abs() < 4.0 * a.error, "rho = {}: {} +- {} vs {}", rho, a.tau_int, a.error, exact);
+            assert!(a.error < 0.05 * exact, "rho = {}: error {}", rho, a.error);
+        }
+    }
+
+    #[test]
+    fn constant_series_is_uncorrelated() {
+        let mut series = TimeSeries::new("constant");
+        for _ in 0..1000 {
+            series.push(1.0);
+        }
+        let a = series.autocorrelation();
+        assert_eq!((a.tau_int, a.error, a.block_size), (0.5, 0.0, 1));
+    }
+}
@@ -0,0 +1,323 @@
+/*********************************************************************************
+    Sweep of a parameter with warm starts
+    -------------------------------------------------------------------
+    "sweep" scans one of h, j, beta or l over a list of values:
+        h=0.8,0.9,1.0,1.1       the listed values
+        h=0.5:1.5:11            11 equally spaced values from 0.5 to 1.5
+    Point k runs in "output/point_<k>" with the other parameters unchanged.
+    Except for the first point and the sweeps over l, each point starts
+    from the equilibrated operator string of the previous one, the factors
+    of the diagonal update being rescaled to the new beta and couplings
+    (see "TFIModel::set_parameters"), so that a shorter thermalization of
+    "num-thm-warm" steps suffices. It defaults to "num-thm", so that the
+    warm starts save no thermalization unless it is set. A point falls
+    back to a cold start when its couplings vanish where the previous
+    string holds operators (e.g. h = 0). A cold start of point k uses the
+    seed derive_seed(seed, k), a warm start continues the Markov chain of
+    the previous point.
+    -------------------------------------------------------------------
+    The bin averages of every point are summarized in "sweep.dat", one
+    row per point (see "run_sweep" for the columns).
+*********************************************************************************/
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::time::Instant;
+use crate::aux::{self, DatFile};
+use crate::config::SimulationConfig;
+use crate::driver::{self, BinRecord, Control, RunSummary, Schedule};
+use crate::error::{Error, Result};
+use crate::stats::Statistics;
+use crate::tfim::{Lattice, SimulationMode, TFIModel};
+
+/// The parameter varied by a sweep
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SweepParameter {
+    /// The transverse field h.
+    H,
+    /// The coupling J.
+    J,
+    /// The inverse temperature beta.
+    Beta,
+    /// The linear size L of the lattice.
+    L,
+}
+
+impl FromStr for SweepParameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "h" => Ok(SweepParameter::H),
+            "j" => Ok(SweepParameter::J),
+            "beta" => Ok(SweepParameter::Beta),
+            "l" => Ok(SweepParameter::L),
+            _ => Err(format!("unknown sweep parameter \"{}\" (expected \"h\", \"j\", \"beta\" or \"l\")", s.trim())),
+        }
+    }
+}
+
+impl fmt::Display for SweepParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SweepParameter::H => write!(f, "h"),
+            SweepParameter::J => write!(f, "j"),
+            SweepParameter::Beta => write!(f, "beta"),
+            SweepParameter::L => write!(f, "l"),
+        }
+    }
+}
+
+/// A sweep of one parameter over a list of values
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sweep {
+    /// The parameter varied.
+    pub parameter: SweepParameter,
+    /// Its values, one point each, in the order of the run.
+    pub values: Vec<f64>,
+}
+
+impl FromStr for Sweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, spec) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected \"parameter=values\", e.g. \"h=0.5:1.5:11\", found \"{}\"", s))?;
+        let parameter: SweepParameter = name.parse()?;
+        let number = |x: &str| x.trim().parse::<f64>().map_err(|_| format!("invalid value \"{}\"", x.trim()));
+
+        let fields: Vec<&str> = spec.split(':').collect();
+        let values: Vec<f64> = match fields.as_slice() {
+            [start, stop, num] => {
+                let (start, stop) = (number(start)?, number(stop)?);
+                let num: usize = num.trim().parse().map_err(|_| format!("invalid number of values \"{}\"", num.trim()))?;
+                match num {
+                    0 => Vec::new(),
+                    1 => vec![start],
+                    _ => (0..num).map(|k| start + (stop - start) * k as f64 / (num - 1) as f64).collect(),
+                }
+            }
+            [list] => list.split(',').map(number).collect::<std::result::Result<_, _>>()?,
+            _ => return Err(format!("expected \"start:stop:num\" or a list \"x,y,...\", found \"{}\"", spec)),
+        };
+        if values.is_empty() {
+            return Err("the sweep has no values".to_string());
+        }
+
+        for &x in values.iter() {
+            let valid = match parameter {
+                SweepParameter::H | SweepParameter::J => x >= 0.0 && x.is_finite(),
+                SweepParameter::Beta => x > 0.0 && x.is_finite(),
+                SweepParameter::L => x >= 1.0 && x.fract() == 0.0 && x <= u32::MAX as f64,
+            };
+            if !valid {
+                return Err(format!("invalid value {} of \"{}\"", x, parameter));
+            }
+        }
+        Ok(Self { parameter, values })
+    }
+}
+
+// e.g. "h=0.8,0.9,1"
+impl fmt::Display for Sweep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.values.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}={}", self.parameter, values.join(","))
+    }
+}
+
+impl Sweep {
+    /// The parameters of point `k`, written to `output/point_<k>`.
+    pub fn point(&self, para: &SimulationConfig, k: usize) -> Result<SimulationConfig> {
+        let mut point = para.clone();
+        let x = self.values[k];
+        match self.parameter {
+            SweepParameter::H => point.h = x,
+            SweepParameter::J => point.j = x,
+            SweepParameter::Beta => point.beta = x,
+            SweepParameter::L => {
+                // a square lattice stays square
+                let lattice = &para.lattice;
+                let ly = if lattice.ly == lattice.lx { x as usize } else { lattice.ly };
+                point.lattice = Lattice::new(lattice.kind, x as usize, ly, lattice.boundary).map_err(Error::Lattice)?;
+            }
+        }
+        point.output = format!("{}/point_{}", para.output, k);
+        point.sweep = None;
+        point.num_thm_warm = None;
+        point.validate()?;
+        Ok(point)
+    }
+}
+
+/// Run the points of the sweep in order, then write "sweep.dat" with the columns
+/// ```text
+///     value, warm (1 for a warm start), n (the average expansion order), and the
+///     mean and error of
+///         purity, renyi2_ee, zz_corr_2_avg, xx_corr_2_avg,
+///         mutual_info2 (finite temperature), renyi2_<name> (each region)
+/// ```
+/// where the correlators are averaged over the sites j and the entropies are the
+/// jackknife estimates from the bins
+/// Each point saves a checkpoint "point_<k>/checkpoint.bin" after every bin.
+/// A "STOP" file in the output directory (or "max-runtime") stops the sweep
+/// after the current bin. Run again, the sweep revisits the points done from
+/// their checkpoints and resumes the stopped one (see output.rs). What the
+/// points have done is returned.
+pub fn run_sweep(para: &SimulationConfig, sweep: &Sweep) -> Result<RunSummary> {
+    let finite_temperature = para.mode == SimulationMode::FiniteTemperature;
+    let mut header = format!("# {}  warm  n  purity  error  renyi2_ee  error  zz_corr_2_avg  error  xx_corr_2_avg  error", sweep.parameter);
+    if finite_temperature {
+        header.push_str("  mutual_info2  error");
+    }
+    for region in para.regions.iter() {
+        header.push_str(&format!("  renyi2_{}  error", region.name));
+    }
+    aux::write_file(format!("{}/sweep.dat", para.output), format!("{}\n", header))?;
+    let mut file_sweep: DatFile = DatFile::append(format!("{}/sweep.dat", para.output))?;
+
+    let start_time = Instant::now();
+    let mut summary = RunSummary::default();
+    let mut model: Option<TFIModel> = None;
+    for k in 0..sweep.values.len() {
+        let point = sweep.point(para, k)?;
+        println!("■ point {} / {}: {} = {}", k + 1, sweep.values.len(), sweep.parameter, sweep.values[k]);
+        fs::create_dir_all(&point.output).map_err(Error::io(&point.output))?;
+        let couplings = point.couplings()?;
+        couplings.save(&format!("{}/couplings.dat", point.output))?;
+
+        // -------------------------------------------------------------
+        //  Warm start from the previous point on the same lattice
+        // -------------------------------------------------------------
+        let warm = match model.as_mut() {
+            Some(model) if sweep.parameter != SweepParameter::L => {
+                match model.set_parameters(point.beta, couplings.clone()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        println!("\t---> Cold start ({})", e);
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+        if !warm {
+            model = Some(point.build_model(couplings, aux::derive_seed(para.seed, k as u32))?);
+        }
+        let model = model.as_mut().unwrap();
+        let schedule = match para.num_thm_warm {
+            Some(num_thm) if warm => Schedule { num_thm, ..point.schedule },
+            _ => point.schedule,
+        };
+        let checkpoint_path = format!("{}/checkpoint.bin", point.output);
+        let control = Control {
+            checkpoint_path: Some(&checkpoint_path),
+            max_runtime: para.max_runtime.map(|max_runtime| max_runtime.saturating_sub(start_time.elapsed())),
+            stop_file: true,
+            stop_dir: Some(&para.output),
+            stop_flag: None,
+            quiet: false,
+        };
+        let outcome = driver::run(model, schedule, &point.output, control)?;
+        summary.add(model, &outcome, schedule.num_bins);
+        if summary.stopped {
+            println!("■ sweep stopped during point {} of {}: run it again to resume", k + 1, sweep.values.len());
+            return Ok(summary);
+        }
+        let records = outcome.records;
+
+        let stats = bin_statistics(&records, &point);
+        let mut row: Vec<f64> = vec![sweep.values[k], if warm { 1.0 } else { 0.0 }];
+        row.push(records.iter().map(|rec| rec.expansion_order).sum::<f64>() / records.len() as f64);
+        let renyi2 = |p: &[f64]| -p[0].ln();
+        let mut push = |mean_and_error: (f64, f64)| row.extend([mean_and_error.0, mean_and_error.1]);
+        let summarized = |name: &str| stats.get(name).map_or((f64::NAN, f64::NAN), |obs| {
+            let s = obs.summarize();
+            (s.mean, s.error)
+        });
+        let jackknifed = |name: &str, inputs: &[&str], f: &dyn Fn(&[f64]) -> f64| {
+            stats.jackknife(name, inputs, f).map_or((f64::NAN, f64::NAN), |d| (d.bias_corrected, d.error))
+        };
+
+        push(summarized("purity"));
+        push(jackknifed("renyi2_ee", &["partial_purity"], &renyi2));
+        push(summarized("zz_corr_2_avg"));
+        push(summarized("xx_corr_2_avg"));
+        if finite_temperature {
+            push(jackknifed("mutual_info2", &["partial_purity", "complement_purity", "purity"], &|p: &[f64]| {
+                -p[0].ln() - p[1].ln() + p[2].ln()
+            }));
+        }
+        for region in point.regions.iter() {
+            let purity_name = format!("purity_{}", region.name);
+            push(jackknifed(&format!("renyi2_{}", region.name), &[&purity_name], &renyi2));
+        }
+        file_sweep.write_str(&format!("{}\n", aux::format_row(&row)))?;
+
+        if k + 1 < sweep.values.len()
+            && let Some(reason) = driver::stop_requested(&para.output)
+        {
+            println!("■ sweep stopped after {} of {} points ({}): run it again to resume", k + 1, sweep.values.len(), reason);
+            summary.stopped = true;
+            return Ok(summary);
+        }
+    }
+    println!("■ sweep over {} points written to \"{}/sweep.dat\"", sweep.values.len(), para.output);
+    Ok(summary)
+}
+
+// the bin averages of a point, with the correlators averaged over the sites
+fn bin_statistics(records: &[BinRecord], point: &SimulationConfig) -> Statistics {
+    let mut stats = Statistics::new();
+    let num_sites = point.lattice.num_sites as f64;
+    for rec in records.iter() {
+        stats.add("purity", rec.purity);
+        stats.add("partial_purity", rec.partial_purity);
+        stats.add("complement_purity", rec.complement_purity);
+        stats.add("zz_corr_2_avg", rec.zz_corr_2.iter().sum::<f64>() / num_sites);
+        stats.add("xx_corr_2_avg", rec.xx_corr_2.iter().sum::<f64>() / num_sites);
+        for (region, p) in point.regions.iter().zip(&rec.region_purity) {
+            stats.add(&format!("purity_{}", region.name), *p);
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges_and_lists() {
+        let sweep: Sweep = "h=0.5:1.5:5".parse().unwrap();
+        assert_eq!(sweep.parameter, SweepParameter::H);
+        assert_eq!(sweep.values, vec![0.5, 0.75, 1.0, 1.25, 1.5]);
+        assert_eq!("Beta=2:4:1".parse::<Sweep>().unwrap().values, vec![2.0]);
+
+        let sweep: Sweep = " J = 0.9, 1 ,1.1".parse().unwrap();
+        assert_eq!((sweep.parameter, sweep.values.clone()), (SweepParameter::J, vec![0.9, 1.0, 1.1]));
+        assert_eq!(sweep.to_string(), "j=0.9,1,1.1");
+        assert_eq!(sweep.to_string().parse::<Sweep>().unwrap(), sweep);
+        assert_eq!("l=8,16".parse::<Sweep>().unwrap().values, vec![8.0, 16.0]);
+    }
+
+    #[test]
+    fn rejects_invalid_sweeps() {
+        for (spec, expected) in [
+            ("h", "expected \"parameter=values\""),
+            ("t=1,2", "unknown sweep parameter \"t\""),
+            ("h=0:1", "expected \"start:stop:num\" or a list"),
+            ("h=0:1:x", "invalid number of values \"x\""),
+            ("h=0:1:0", "the sweep has no values"),
+            ("h=0.5,x", "invalid value \"x\""),
+            ("h=-0.5,1", "invalid value -0.5 of \"h\""),
+            ("beta=0,1", "invalid value 0 of \"beta\""),
+            ("l=8.5", "invalid value 8.5 of \"l\""),
+            ("l=0", "invalid value 0 of \"l\""),
+        ] {
+            let error = spec.parse::<Sweep>().unwrap_err();
+            assert!(error.starts_with(expected), "{:?}: {}", spec, error);
+        }
+    }
+}
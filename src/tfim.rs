@@ -1,29 +1,50 @@
-use prng_mt::mt19937::MT19937;
-pub mod init;
-pub mod random;
-pub mod updates;
-pub mod measure;
-pub mod stack;
+use crate::error::{Error, Result};
+use crate::mt19937::MT19937;
+mod init;
+mod random;
+mod updates;
+mod measure;
+mod stack;
+mod lattice;
+mod couplings;
+mod alias;
+mod mode;
+mod region;
+mod pauli;
+mod checkpoint;
+pub use lattice::{Boundary, Lattice, LatticeKind};
+pub use couplings::{Couplings, Disorder};
+pub use mode::SimulationMode;
+pub use region::Region;
+pub use pauli::{Pauli, PauliPair, PauliString};
+use alias::AliasTable;
 
+/// The Bell-basis SSE of the TFIM: build it with [`TFIModel::new`] and
+/// [`TFIModel::init`], choose the measurements with the `set_*` methods,
+/// thermalize with [`TFIModel::mc_thermalizing`], and measure bins with
+/// [`TFIModel::ini_measure`], [`TFIModel::mc_sampling`] and
+/// [`TFIModel::statisticize`].
 pub struct TFIModel {
     // ----------------------------------------------------------------
     //  Basic params
     // ----------------------------------------------------------------
-    l: usize,
-    beta: f64,
+    beta: f64,                  // inverse temperature of each copy (the SSE runs at 2 * beta)
+    mode: SimulationMode,
 
-    j: f64,     // strength of ZZ couplings
-    h: f64,     // strength of the external fields
+    couplings: Couplings,       // J_b of the ZZ couplings and h_i of the external fields
 
     n: usize,       // number of null operators
-    pub m: usize,       // truncation order of the series
+    m: usize,       // truncation order of the series
 
     // --------------------------------------------------------
     //  Lattice
     // --------------------------------------------------------
-    pub num_sites: usize,             // number of sites
+    lattice: Lattice,                 // record the linking of the lattice
+    num_sites: usize,                 // number of sites
     num_bonds: usize,                 // number of bonds
-    b_sites: Vec<Vec<usize>>,         // record the linking of the lattice
+    num_dual_bonds: usize,            // number of bonds in the dual picture (including virtual ones)
+    num_legs: usize,                  // legs reserved for each operator in the vertex lists
+    cycles: Vec<Vec<usize>>,          // a basis of the cycles of the bonds (see "winding_update.rs")
 
     // --------------------------------------------------------
     //  Some frequently-used factors
@@ -31,6 +52,8 @@ pub struct TFIModel {
     selection_prob: f64,
     add_factor: f64,
     remove_factor: f64,
+    site_table: AliasTable,     // draw a site "i" with probability h_i / sum(h)
+    bond_table: AliasTable,     // draw a bond "b" with probability J_b / sum(J)
 
     // -------------------------------------------------
     //  PRNG with MT19937
@@ -59,15 +82,219 @@ pub struct TFIModel {
     //  Measurements related
     // ----------------------------------------
     subsystem: Vec<usize>,
+    complement: Vec<usize>,     // the rest of the system, measured at finite temperature
     system: Vec<usize>,
-    pub purity: f64,
-    pub partial_purity: f64, 
-    pub zz_corr_2: Vec<f64>,    // record the correlations between the 0th site and others
-    pub xx_corr_2: Vec<f64>, 
+    regions: Vec<Region>,       // user-defined regions, each with its own accumulator
+    num_steps: usize,           // MC steps sampled since "ini_measure"
+    purity: f64,
+    partial_purity: f64, 
+    complement_purity: f64,
+    region_purity: Vec<f64>,
+    profile_purity: Vec<f64>,   // purities of the cuts [0, l) for l = 1, ..., num_sites - 1
+    ref_site: usize,            // reference site of "zz_corr_2" and "xx_corr_2"
+    corr_matrix: bool,          // whether the all-pairs matrices are measured
+    zz_corr_2: Vec<f64>,        // record the correlations between the reference site and others
+    xx_corr_2: Vec<f64>, 
+    pauli_pairs: Vec<PauliPair>,    // further two-site correlators "PQ" with the reference site
+    pauli_corr_2: Vec<Vec<f64>>,
+    pauli_strings: Vec<PauliString>,    // Pauli strings P with the estimates of tr(rho P)^2
+    pauli_string_2: Vec<f64>,
+    zz_corr_matrix: Vec<f64>,   // num_sites x num_sites, row-major (empty unless enabled)
+    xx_corr_matrix: Vec<f64>,
+    pauli_z: Vec<f64>,          // Z_i and X_i of the current slice, for the matrices
+    pauli_x: Vec<f64>,
+}
+impl TFIModel {
+    /// Ground state projection or finite temperature.
+    #[inline]
+    pub fn mode(&self) -> SimulationMode {
+        self.mode
+    }
 
-    // ----------------------------------------
-    //  For testing the parity
-    // ----------------------------------------
-    pub num_parity_odd: usize,
-    pub num_parity_even: usize,
-}
\ No newline at end of file
+    /// The expansion order n, the number of non-null operators in the string.
+    #[inline]
+    pub fn expansion_order(&self) -> usize {
+        self.n
+    }
+
+    /// The lattice of the sites and bonds.
+    #[inline]
+    pub fn lattice(&self) -> &Lattice {
+        &self.lattice
+    }
+
+    /// The inverse temperature of each copy (the SSE runs at `2 * beta`).
+    #[inline]
+    pub fn beta(&self) -> f64 {
+        self.beta
+    }
+
+    /// The cut-off of the operator string, grown by the thermalization.
+    #[inline]
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// The number of sites of the lattice.
+    #[inline]
+    pub fn num_sites(&self) -> usize {
+        self.num_sites
+    }
+
+    /// The Bell sample at the zero time, one qudit (r^z, r^x) per site:
+    /// the Pauli string 00 ~ I, 01 ~ X, 10 ~ Z, 11 ~ Y of the Bell pairs.
+    #[inline]
+    pub fn qudits(&self) -> &[u8] {
+        &self.qudits
+    }
+
+    /// The regions of [`TFIModel::region_purity`], in order.
+    #[inline]
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// The reference site r of the two-site correlators.
+    #[inline]
+    pub fn ref_site(&self) -> usize {
+        self.ref_site
+    }
+
+    /// The reference site r of `zz_corr_2`, `xx_corr_2` and `pauli_corr_2`.
+    pub fn set_ref_site(&mut self, ref_site: usize) -> Result<()> {
+        if ref_site >= self.num_sites {
+            return Err(Error::Config(format!("reference site {} out of range (< {})", ref_site, self.num_sites)));
+        }
+        self.ref_site = ref_site;
+        Ok(())
+    }
+
+    /// The further correlators "PQ" of [`TFIModel::pauli_corr_2`], in order.
+    #[inline]
+    pub fn pauli_pairs(&self) -> &[PauliPair] {
+        &self.pauli_pairs
+    }
+
+    /// Further squared correlators tr(rho P_r Q_j)^2, in `pauli_corr_2`.
+    pub fn set_pauli_pairs(&mut self, pauli_pairs: Vec<PauliPair>) {
+        self.pauli_corr_2 = vec![vec![0.0; self.num_sites]; pauli_pairs.len()];
+        self.pauli_pairs = pauli_pairs;
+    }
+
+    /// The Pauli strings of [`TFIModel::pauli_string_2`], in order.
+    #[inline]
+    pub fn pauli_strings(&self) -> &[PauliString] {
+        &self.pauli_strings
+    }
+
+    /// Pauli strings P with the estimates of tr(rho P)^2, in `pauli_string_2`.
+    pub fn set_pauli_strings(&mut self, pauli_strings: Vec<PauliString>) {
+        self.pauli_string_2 = vec![0.0; pauli_strings.len()];
+        self.pauli_strings = pauli_strings;
+    }
+
+    /// Whether the all-pairs matrices are measured.
+    #[inline]
+    pub fn corr_matrix(&self) -> bool {
+        self.corr_matrix
+    }
+
+    /// Measure the squared correlators of all pairs, in O(num_sites^2) per time slice.
+    pub fn set_corr_matrix(&mut self, enabled: bool) {
+        let size = if enabled { self.num_sites * self.num_sites } else { 0 };
+        self.corr_matrix = enabled;
+        self.zz_corr_matrix = vec![0.0; size];
+        self.xx_corr_matrix = vec![0.0; size];
+        self.pauli_z = vec![0.0; if enabled { self.num_sites } else { 0 }];
+        self.pauli_x = vec![0.0; if enabled { self.num_sites } else { 0 }];
+    }
+
+    /// The regions whose purities are measured besides the half system,
+    /// an error for a site out of range or given twice in a region.
+    pub fn set_regions(&mut self, regions: Vec<Region>) -> Result<()> {
+        for region in regions.iter() {
+            if let Some(&s) = region.sites.iter().find(|&&s| s >= self.num_sites) {
+                return Err(Error::Config(format!("region \"{}\": site {} out of range (< {})", region.name, s, self.num_sites)));
+            }
+            if let Some(&s) = region.sites.iter().enumerate().find_map(|(i, s)| region.sites[..i].contains(s).then_some(s)) {
+                return Err(Error::Config(format!("region \"{}\": site {} given twice", region.name, s)));
+            }
+        }
+        self.region_purity = vec![0.0; regions.len()];
+        self.regions = regions;
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+//  The measurements: accumulators while sampling, bin averages after
+//  "statisticize"
+// ----------------------------------------------------------------------
+impl TFIModel {
+    /// tr(rho^2) of the whole system (1 for the ground state).
+    #[inline]
+    pub fn purity(&self) -> f64 {
+        self.purity
+    }
+
+    /// tr(rho_A^2) of the half system A, i.e. exp(-S2(A)).
+    #[inline]
+    pub fn partial_purity(&self) -> f64 {
+        self.partial_purity
+    }
+
+    /// tr(rho_B^2) of the other half B, measured at finite temperature only.
+    #[inline]
+    pub fn complement_purity(&self) -> f64 {
+        self.complement_purity
+    }
+
+    /// tr(rho_R^2) of each of the [`TFIModel::regions`].
+    #[inline]
+    pub fn region_purity(&self) -> &[f64] {
+        &self.region_purity
+    }
+
+    /// tr(rho^2) of the cuts [0, l) for l = 1, ..., num_sites - 1.
+    #[inline]
+    pub fn profile_purity(&self) -> &[f64] {
+        &self.profile_purity
+    }
+
+    /// tr(rho Z_r Z_j)^2 for the reference site r and every site j.
+    #[inline]
+    pub fn zz_corr_2(&self) -> &[f64] {
+        &self.zz_corr_2
+    }
+
+    /// tr(rho X_r X_j)^2 for the reference site r and every site j.
+    #[inline]
+    pub fn xx_corr_2(&self) -> &[f64] {
+        &self.xx_corr_2
+    }
+
+    /// tr(rho P_r Q_j)^2 of each of the [`TFIModel::pauli_pairs`], for every site j.
+    #[inline]
+    pub fn pauli_corr_2(&self) -> &[Vec<f64>] {
+        &self.pauli_corr_2
+    }
+
+    /// tr(rho P)^2 of each of the [`TFIModel::pauli_strings`].
+    #[inline]
+    pub fn pauli_string_2(&self) -> &[f64] {
+        &self.pauli_string_2
+    }
+
+    /// tr(rho Z_i Z_j)^2 of all pairs, num_sites x num_sites row-major
+    /// (empty unless [`TFIModel::set_corr_matrix`]).
+    #[inline]
+    pub fn zz_corr_matrix(&self) -> &[f64] {
+        &self.zz_corr_matrix
+    }
+
+    /// tr(rho X_i X_j)^2 of all pairs, like [`TFIModel::zz_corr_matrix`].
+    #[inline]
+    pub fn xx_corr_matrix(&self) -> &[f64] {
+        &self.xx_corr_matrix
+    }
+}
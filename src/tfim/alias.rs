@@ -0,0 +1,53 @@
+/*********************************************************************************
+    Walker's alias table (Vose's construction) for drawing an index "i"
+    with probability w_i / sum(w) in O(1):
+        pick i uniformly, keep it with probability prob[i],
+        otherwise take alias[i]
+    -------------------------------------------------------------------
+    For uniform weights every prob[i] is exactly 1, so that no second
+    random number is needed and the uniform case costs the same as before.
+*********************************************************************************/
+
+#[derive(Clone, Debug)]
+pub struct AliasTable {
+    pub prob: Vec<f64>,
+    pub alias: Vec<usize>,
+}
+
+impl AliasTable {
+    pub fn new(weights: &[f64]) -> Self {
+        let n = weights.len();
+        let total: f64 = weights.iter().sum();
+
+        // ---------------------------------------------------------------
+        //  Uniform (or empty) weights: every index keeps itself
+        // ---------------------------------------------------------------
+        if total <= 0.0 || weights.iter().all(|&w| w == weights[0]) {
+            return Self { prob: vec![1.0; n], alias: (0..n).collect() };
+        }
+
+        let mut prob: Vec<f64> = weights.iter().map(|&w| w * n as f64 / total).collect();
+        let mut alias: Vec<usize> = (0..n).collect();
+
+        let mut small: Vec<usize> = (0..n).filter(|&i| prob[i] < 1.0).collect();
+        let mut large: Vec<usize> = (0..n).filter(|&i| prob[i] >= 1.0).collect();
+
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            alias[s] = l;
+            prob[l] -= 1.0 - prob[s];
+
+            if prob[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+
+        // whatever remains is 1 up to the round-off
+        for i in small.into_iter().chain(large) {
+            prob[i] = 1.0;
+        }
+
+        Self { prob, alias }
+    }
+}
@@ -0,0 +1,159 @@
+/*********************************************************************************
+    The model part of a checkpoint (see "checkpoint.rs" for the file)
+        parameters      num_sites, num_bonds, num_dual_bonds, beta, mode,
+                        checked against the model being restored
+        measurements    the reference site, the regions (names and sites),
+                        the further correlators and the Pauli strings (their
+                        factors), also checked, so that no bins of other
+                        observables are mixed in
+        couplings       J_b and h_i, checked exactly as well: a resume
+                        never switches to other couplings
+        configuration   m, n, operator string, qudits, MT19937 state
+        accumulators    purities and correlators of the current bin
+    The work arrays (vertex lists, stacks, qudits at each time slice) are
+    rebuilt by every MC step and only resized from "m".
+*********************************************************************************/
+use crate::checkpoint::{Reader, Writer};
+use crate::mt19937::MT19937;
+use crate::tfim::TFIModel;
+
+impl TFIModel {
+    pub(crate) fn write_checkpoint(&self, w: &mut Writer) {
+        w.usize(self.num_sites);
+        w.usize(self.num_bonds);
+        w.usize(self.num_dual_bonds);
+        w.f64(self.beta);
+        w.str(&self.mode.to_string());
+        let measurements = self.measurements();
+        w.usize(measurements.len());
+        measurements.iter().for_each(|m| w.str(m));
+
+        w.f64s(&self.couplings.j);
+        w.f64s(&self.couplings.h);
+
+        w.usize(self.m);
+        w.usize(self.n);
+        w.i32s(&self.op_string);
+        w.u8s(&self.qudits);
+        let (state, index) = self.rng.state();
+        w.u32s(state);
+        w.usize(index);
+
+        w.f64(self.purity);
+        w.f64(self.partial_purity);
+        w.f64(self.complement_purity);
+        w.f64s(&self.region_purity);
+        w.f64s(&self.profile_purity);
+        w.f64s(&self.zz_corr_2);
+        w.f64s(&self.xx_corr_2);
+        w.usize(self.pauli_corr_2.len());
+        self.pauli_corr_2.iter().for_each(|corr| w.f64s(corr));
+        w.f64s(&self.pauli_string_2);
+        w.f64s(&self.zz_corr_matrix);
+        w.f64s(&self.xx_corr_matrix);
+    }
+
+    pub(crate) fn read_checkpoint(&mut self, r: &mut Reader) -> Result<(), String> {
+        // -----------------------------------------------
+        //  The parameters must match the current model
+        // -----------------------------------------------
+        let sizes = (r.usize()?, r.usize()?, r.usize()?);
+        if sizes != (self.num_sites, self.num_bonds, self.num_dual_bonds) {
+            return Err(format!(
+                "checkpoint lattice has (sites, bonds, dual bonds) = {:?}, expected {:?}",
+                sizes, (self.num_sites, self.num_bonds, self.num_dual_bonds)
+            ));
+        }
+        let beta = r.f64()?;
+        let mode = r.str()?;
+        if beta != self.beta || mode != self.mode.to_string() {
+            return Err(format!(
+                "checkpoint has beta = {}, mode = {}, expected beta = {}, mode = {}", beta, mode, self.beta, self.mode
+            ));
+        }
+
+        let num_measurements = r.usize()?;
+        let measurements: Vec<String> = (0..num_measurements).map(|_| r.str()).collect::<Result<_, _>>()?;
+        let expected = self.measurements();
+        if measurements != expected {
+            let i = (0..).find(|&i| measurements.get(i) != expected.get(i)).unwrap();
+            let describe = |m: Option<&String>| m.map_or("nothing more".to_string(), |m| format!("\"{}\"", m));
+            return Err(format!("checkpoint measures {}, expected {}", describe(measurements.get(i)), describe(expected.get(i))));
+        }
+
+        let j = r.f64s()?;
+        let h = r.f64s()?;
+        if let Some(b) = (0..self.num_bonds).find(|&b| j.get(b) != Some(&self.couplings.j[b])) {
+            return Err(format!("checkpoint has J_{} = {:?}, expected {}", b, j.get(b), self.couplings.j[b]));
+        }
+        if let Some(i) = (0..self.num_sites).find(|&i| h.get(i) != Some(&self.couplings.h[i])) {
+            return Err(format!("checkpoint has h_{} = {:?}, expected {}", i, h.get(i), self.couplings.h[i]));
+        }
+        if (j.len(), h.len()) != (self.num_bonds, self.num_sites) {
+            return Err("checkpoint couplings do not match the lattice".to_string());
+        }
+
+        // ------------------------------------
+        //  Configuration
+        // ------------------------------------
+        self.m = r.usize()?;
+        self.n = r.usize()?;
+        self.op_string = r.i32s()?;
+        self.qudits = r.u8s()?;
+        if self.op_string.len() != self.m || self.qudits.len() != self.num_sites || self.qudits.iter().any(|&q| q > 0b11) {
+            return Err("inconsistent configuration in checkpoint".to_string());
+        }
+        let state = r.u32s()?;
+        let index = r.usize()?;
+        self.rng = MT19937::from_state(&state, index)?;
+        self.check_op_string().map_err(|e| e.to_string())?;
+        self.init_buffers();
+
+        // ------------------------------------------------------------
+        //  Accumulators, sized by the measurements set on the model
+        // ------------------------------------------------------------
+        self.purity = r.f64()?;
+        self.partial_purity = r.f64()?;
+        self.complement_purity = r.f64()?;
+        let mismatch = |name: &str| format!("checkpoint \"{}\" does not match the requested measurements", name);
+        read_into(r, &mut self.region_purity).map_err(|_| mismatch("regions"))?;
+        read_into(r, &mut self.profile_purity).map_err(|_| mismatch("profile"))?;
+        read_into(r, &mut self.zz_corr_2).map_err(|_| mismatch("zz_corr_2"))?;
+        read_into(r, &mut self.xx_corr_2).map_err(|_| mismatch("xx_corr_2"))?;
+        if r.usize()? != self.pauli_corr_2.len() {
+            return Err(mismatch("correlators"));
+        }
+        for corr in self.pauli_corr_2.iter_mut() {
+            read_into(r, corr).map_err(|_| mismatch("correlators"))?;
+        }
+        read_into(r, &mut self.pauli_string_2).map_err(|_| mismatch("Pauli strings"))?;
+        read_into(r, &mut self.zz_corr_matrix).map_err(|_| mismatch("correlation matrices"))?;
+        read_into(r, &mut self.xx_corr_matrix).map_err(|_| mismatch("correlation matrices"))?;
+
+        Ok(())
+    }
+}
+
+impl TFIModel {
+    // the measurement setup, e.g. "ref_site 0", "region A=0,1", "corr YY", "pauli X0 Z3"
+    fn measurements(&self) -> Vec<String> {
+        let mut measurements = vec![format!("ref_site {}", self.ref_site)];
+        measurements.extend(self.regions.iter().map(|region| format!("region {}", region)));
+        measurements.extend(self.pauli_pairs.iter().map(|pair| format!("corr {}", pair)));
+        for pauli_string in self.pauli_strings.iter() {
+            let ops: Vec<String> = pauli_string.ops.iter().map(|(s, pauli)| format!("{}{}", pauli, s)).collect();
+            measurements.push(format!("pauli {}", ops.join(" ")));
+        }
+        measurements
+    }
+}
+
+// read a vector of the same length as "target" into it
+fn read_into(r: &mut Reader, target: &mut [f64]) -> Result<(), String> {
+    let values = r.f64s()?;
+    if values.len() != target.len() {
+        return Err("length mismatch".to_string());
+    }
+    target.copy_from_slice(&values);
+    Ok(())
+}
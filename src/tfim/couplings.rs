@@ -0,0 +1,156 @@
+/*********************************************************************************
+    Couplings of the TFIM
+        H = - sum_b J_b Z_i Z_j - sum_i h_i X_i
+    with J_b >= 0 on every bond and h_i >= 0 on every site.
+    -------------------------------------------------------------------
+    A couplings file lists the entries that differ from the uniform
+    values, one per line ("#" starts a comment):
+        # kind  index  value
+        J       0      1.0
+        h       3      0.5
+    -------------------------------------------------------------------
+    Random couplings (disordered TFIM) are drawn with their own PRNG, so
+    that a realization only depends on the disorder seed:
+        box      J_b ~ U(0, J),  h_i ~ U(0, h)
+        log:W    ln(J_b / J), ln(h_i / h) ~ U(-W, 0)
+*********************************************************************************/
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use crate::aux;
+use crate::error;
+use crate::mt19937::MT19937;
+use crate::tfim::Lattice;
+
+/// The distribution of random couplings, "box" or "log:W".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Disorder {
+    /// J_b ~ U(0, J) and h_i ~ U(0, h).
+    Box,
+    /// ln(J_b / J) and ln(h_i / h) ~ U(-W, 0).
+    Log(f64),
+}
+
+impl FromStr for Disorder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        if lower == "box" {
+            return Ok(Disorder::Box);
+        }
+
+        match lower.strip_prefix("log:").map(|w| w.parse::<f64>()) {
+            Some(Ok(w)) if w > 0.0 => Ok(Disorder::Log(w)),
+            _ => Err(format!("unknown disorder \"{}\" (expected \"box\" or \"log:W\" with W > 0)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Disorder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Disorder::Box => write!(f, "box"),
+            Disorder::Log(w) => write!(f, "log:{}", w),
+        }
+    }
+}
+
+/// The couplings J_b of the bonds and the fields h_i of the sites.
+#[derive(Clone, Debug)]
+pub struct Couplings {
+    /// J_b on the (physical) bonds.
+    pub j: Vec<f64>,
+    /// h_i on the sites.
+    pub h: Vec<f64>,
+}
+
+impl Couplings {
+    /// J on every bond and h on every site.
+    pub fn uniform(lattice: &Lattice, j: f64, h: f64) -> Self {
+        Self {
+            j: vec![j; lattice.num_bonds()],
+            h: vec![h; lattice.num_sites],
+        }
+    }
+
+    /// Random couplings of the `disorder` distribution drawn with `seed`.
+    pub fn random(lattice: &Lattice, j: f64, h: f64, disorder: Disorder, seed: u32) -> Self {
+        let mut rng = MT19937::new(seed);
+        let mut draw = |scale: f64| -> f64 {
+            // uniform in (0, 1), so that no coupling vanishes exactly
+            let u = (rng.next_u32() as f64 + 0.5) / (u32::MAX as f64 + 1.0);
+            match disorder {
+                Disorder::Box => scale * u,
+                Disorder::Log(w) => scale * (-w * u).exp(),
+            }
+        };
+
+        let j_b: Vec<f64> = (0..lattice.num_bonds()).map(|_| draw(j)).collect();
+        let h_i: Vec<f64> = (0..lattice.num_sites).map(|_| draw(h)).collect();
+
+        Self { j: j_b, h: h_i }
+    }
+
+    /// The uniform couplings with the entries of a couplings file.
+    pub fn from_file(lattice: &Lattice, j: f64, h: f64, file_path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("cannot read couplings file \"{}\": {}", file_path, e))?;
+        let mut couplings = Self::uniform(lattice, j, h);
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |msg: &str| format!("{}:{}: {}", file_path, i + 1, msg);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 {
+                return Err(error("expected \"<J|h> <index> <value>\""));
+            }
+
+            let index: usize = fields[1].parse().map_err(|_| error("invalid index"))?;
+            let value: f64 = fields[2].parse().map_err(|_| error("invalid value"))?;
+            let target = match fields[0] {
+                "J" | "j" => &mut couplings.j,
+                "h" | "H" => &mut couplings.h,
+                _ => return Err(error("the kind must be \"J\" or \"h\"")),
+            };
+
+            match target.get_mut(index) {
+                Some(entry) => *entry = value,
+                None => return Err(error(&format!("index {} out of range (< {})", index, target.len()))),
+            }
+        }
+
+        Ok(couplings)
+    }
+
+    /// Write every coupling in the format of a couplings file.
+    pub fn save(&self, file_path: &str) -> error::Result<()> {
+        let mut content = String::from("# kind  index  value\n");
+        for (b, j) in self.j.iter().enumerate() {
+            content.push_str(&format!("J\t{}\t{}\n", b, j));
+        }
+        for (s, h) in self.h.iter().enumerate() {
+            content.push_str(&format!("h\t{}\t{}\n", s, h));
+        }
+        aux::write_file(file_path.to_string(), content)
+    }
+
+    /// The sum of the J_b.
+    pub fn total_j(&self) -> f64 {
+        self.j.iter().sum()
+    }
+
+    /// The sum of the h_i.
+    pub fn total_h(&self) -> f64 {
+        self.h.iter().sum()
+    }
+
+    /// Whether all J_b and all h_i are equal.
+    pub fn is_uniform(&self) -> bool {
+        self.j.iter().all(|&j| j == self.j[0]) && self.h.iter().all(|&h| h == self.h[0])
+    }
+}
@@ -1,26 +1,49 @@
-use prng_mt::MT19937;
-use crate::tfim::TFIModel;
+use crate::mt19937::MT19937;
+use crate::tfim::{TFIModel, Lattice, Couplings, SimulationMode};
+use crate::tfim::alias::AliasTable;
 use crate::aux::{NULL_OP, NULL_QUDIT, EMPTY};
+use crate::error::{Error, Result};
+
+// the cut-off of the empty operator string, grown during thermalization
+const INITIAL_M: usize = 10;
 
 impl TFIModel {
-    pub fn new(para_l: usize, para_beta: f64, para_j: f64, para_h: f64, para_seed: u32) -> Self {
-        Self {
+    /// A model of the TFIM with the couplings on the lattice, each copy of
+    /// rho at inverse temperature `para_beta`; call [`TFIModel::init`] next.
+    pub fn new(para_lattice: Lattice, para_beta: f64, para_couplings: Couplings, para_seed: u32, para_mode: SimulationMode) -> Result<Self> {
+        // ---------------------------------------------------------------
+        //  A lattice without bonds (e.g. l = 1) has nothing to sample
+        // ---------------------------------------------------------------
+        let num_sites = para_lattice.num_sites;
+        if num_sites < 2 || para_lattice.num_bonds() == 0 {
+            return Err(Error::Lattice(format!(
+                "{} has {} sites and {} bonds, at least 2 sites and 1 bond are required",
+                para_lattice, num_sites, para_lattice.num_bonds()
+            )));
+        }
+        if !(para_beta > 0.0 && para_beta.is_finite()) {
+            return Err(Error::Model(format!("beta must be positive and finite, got {}", para_beta)));
+        }
+
+        let model = Self {
             // ----------------------------------------------------------------
             //  Basic params
             // ----------------------------------------------------------------
-            l: para_l,
             beta: para_beta,
-            j: para_j,
-            h: para_h,
+            mode: para_mode,
+            couplings: para_couplings,
             n: 0,
-            m: 10,
+            m: INITIAL_M,
 
             // --------------------------------------------------------
             //  Lattice
             // --------------------------------------------------------
-            num_sites: para_l,
+            lattice: para_lattice,
+            num_sites,
             num_bonds: 0,                 // default
-            b_sites: Vec::new(),         // default
+            num_dual_bonds: 0,            // default
+            num_legs: 4,                  // default
+            cycles: Vec::new(),           // default
 
             // --------------------------------------------------------
             //  Some frequently-used factors
@@ -28,6 +51,8 @@ impl TFIModel {
             selection_prob: 0.0,    // default
             add_factor: 0.0,        // default
             remove_factor: 0.0,     // default
+            site_table: AliasTable::new(&[]),   // default
+            bond_table: AliasTable::new(&[]),   // default
 
             // ----------------------------------------
             //  Random number generator
@@ -58,80 +83,177 @@ impl TFIModel {
             // ----------------------------------------
             system: Vec::new(),
             subsystem: Vec::new(),
+            complement: Vec::new(),
+            regions: Vec::new(),
+            num_steps: 0,
             partial_purity: 0.0, 
+            complement_purity: 0.0,
+            region_purity: Vec::new(),
+            profile_purity: vec![0.0; num_sites.saturating_sub(1)],
             purity: 0.0,
-            zz_corr_2: vec![0.0; para_l],
-            xx_corr_2: vec![0.0; para_l],
+            ref_site: 0,
+            corr_matrix: false,
+            zz_corr_2: vec![0.0; num_sites],
+            xx_corr_2: vec![0.0; num_sites],
+            pauli_pairs: Vec::new(),
+            pauli_corr_2: Vec::new(),
+            pauli_strings: Vec::new(),
+            pauli_string_2: Vec::new(),
+            zz_corr_matrix: Vec::new(),
+            xx_corr_matrix: Vec::new(),
+            pauli_z: Vec::new(),
+            pauli_x: Vec::new(),
+        };
+        model.check_couplings()?;
+        Ok(model)
+    }
 
-            // ----------------------------------------
-            //  For testing the parity
-            // ----------------------------------------
-            num_parity_odd: 0,
-            num_parity_even: 0,
+    /// Attach the ghost bonds (finite temperature), set up the factors of the
+    /// diagonal update and start from the empty operator string, with n = 0
+    /// and the initial cut-off m (also when called again).
+    pub fn init(&mut self) -> Result<()> {
+        // ---------------------------------------------------------------------
+        //  The lattice supplies the bonds and the dual bonds. A site operator
+        //  links its dual bonds pair by pair in the dual vertex list, each pair
+        //  using four legs, while a bond operator uses at most four legs.
+        // ---------------------------------------------------------------------
+        if self.mode == SimulationMode::FiniteTemperature {
+            self.lattice.attach_ghost_bonds();
         }
-    }
+        self.num_bonds = self.lattice.num_bonds();
+        self.num_dual_bonds = self.lattice.num_dual_bonds();
+        self.num_legs = 4 * self.lattice.max_pairs();
+        self.cycles = self.lattice.cycles();
 
-    pub fn init(&mut self) {
-        self.num_bonds = self.l - 1;
+        self.init_factors()?;
 
-        // ------------------------------------
-        //  Make the lattice (OBC)
-        // ------------------------------------
+        // --------------------------------------------
+        //  Initialize the initial states
+        // --------------------------------------------
+        self.qudits = vec![0; self.num_sites];
+
+        // --------------------------------------------------------
+        //  Initialize data structures related to operator string
+        // --------------------------------------------------------
+        self.n = 0;
+        self.m = INITIAL_M;
+        self.op_string = vec![NULL_OP; self.m];
+        self.init_buffers();
 
+        // -------------------------------------------
+        //  Decide the environment to be traced out
+        // -------------------------------------------
+        self.system = (0..self.num_sites).collect();
+        self.subsystem = (0..(self.num_sites / 2)).collect();
+        self.complement = ((self.num_sites / 2)..self.num_sites).collect();
+        Ok(())
+    }
 
-        self.b_sites = {
-            let mut lattice: Vec<Vec<usize>> = Vec::new();
-            //  make the 1D lattice (OBC)
-            for i in 0..(self.num_sites - 1) {
-                lattice.push(vec![i, i + 1]);
-            }
-            lattice.clone()
-        };
+    // the factors of the diagonal update and the alias tables, from the couplings
+    pub(crate) fn init_factors(&mut self) -> Result<()> {
+        self.check_couplings()?;
 
         // --------------------------------------------
         //  Initialize the frequently-used factors
         // --------------------------------------------
-        self.selection_prob = self.h * self.num_sites as f64 / (
-            self.h * self.num_sites as f64 + self.j * self.num_bonds as f64 
-        );
+        let total_h = self.couplings.total_h();
+        let total_j = self.couplings.total_j();
 
-        self.add_factor = self.beta * (
-            self.h * self.num_sites as f64 + self.j * self.num_bonds as f64
-        );
+        self.selection_prob = total_h / (total_h + total_j);
+
+        // the two copies rho ⊗ rho are simulated at 2 * beta (see "mode.rs")
+        self.add_factor = 2.0 * self.beta * (total_h + total_j);
         self.remove_factor = 1.0 / self.add_factor;
 
-        // --------------------------------------------
-        //  Initialize the initial states
-        // --------------------------------------------
-        // self.qudits = (0..self.num_sites)
-        //     .map(|_| self.rand_qudit())
-        //     .collect();
-        self.qudits = vec![0; self.num_sites];
+        self.site_table = AliasTable::new(&self.couplings.h);
+        self.bond_table = AliasTable::new(&self.couplings.j);
+        Ok(())
+    }
+
+    /// Continue from the current configuration with another `beta` and
+    /// other couplings on the same lattice (the warm start of a sweep):
+    /// the operator string and the cut-off stay, only the factors of the
+    /// diagonal update are rescaled. An operator on a coupling that now
+    /// vanishes would have zero weight, so that is an error.
+    pub fn set_parameters(&mut self, beta: f64, couplings: Couplings) -> Result<()> {
+        if !(beta > 0.0 && beta.is_finite()) {
+            return Err(Error::Model(format!("beta must be positive and finite, got {}", beta)));
+        }
+        for &op in self.op_string.iter().filter(|&&op| op != NULL_OP) {
+            let (i, t) = ((op / 4) as usize, op % 4);
+            let vanishes = if t < 2 { couplings.h.get(i) == Some(&0.0) } else { couplings.j.get(i) == Some(&0.0) };
+            if vanishes {
+                return Err(Error::Model(format!(
+                    "the operator string holds a {} operator where the coupling vanishes", if t < 2 { "site" } else { "bond" }
+                )));
+            }
+        }
+        let previous = (self.beta, std::mem::replace(&mut self.couplings, couplings));
+        self.beta = beta;
+        if let Err(e) = self.init_factors() {
+            (self.beta, self.couplings) = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
 
-         // --------------------------------------------------------------------
+    // one finite, non-negative J_b per bond and h_i per site, not all zero
+    fn check_couplings(&self) -> Result<()> {
+        let num_bonds = self.lattice.num_bonds();
+        if self.couplings.j.len() != num_bonds || self.couplings.h.len() != self.num_sites {
+            return Err(Error::Model(format!(
+                "expected {} J_b and {} h_i, got {} and {}",
+                num_bonds, self.num_sites, self.couplings.j.len(), self.couplings.h.len()
+            )));
+        }
+        if !self.couplings.j.iter().chain(self.couplings.h.iter()).all(|&x| x >= 0.0 && x.is_finite()) {
+            return Err(Error::Model("the couplings J_b and h_i must be finite and non-negative".to_string()));
+        }
+        if self.couplings.total_h() + self.couplings.total_j() <= 0.0 {
+            return Err(Error::Model("at least one coupling must be positive".to_string()));
+        }
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------
+    //  Every operator is null or "4 i + t", with a site "i" for t = 0, 1
+    //  and a bond "i" for t = 2, 3, and there are "n" non-null ones
+    // ------------------------------------------------------------------
+    /// Check the operator string, e.g. after reading a checkpoint.
+    pub fn check_op_string(&self) -> Result<()> {
+        let mut n: usize = 0;
+        for (p, &op) in self.op_string.iter().enumerate() {
+            if op == NULL_OP {
+                continue;
+            }
+            let range = if op % 4 < 2 { self.num_sites } else { self.num_bonds };
+            if op < 0 || (op / 4) as usize >= range {
+                return Err(Error::InvalidOperator { p, op });
+            }
+            n += 1;
+        }
+        if n != self.n {
+            return Err(Error::Model(format!("{} operators in the string, expected n = {}", n, self.n)));
+        }
+        Ok(())
+    }
+
+    // the work arrays sized by the cut-off "m" (the operator string excepted)
+    pub(crate) fn init_buffers(&mut self) {
+        // --------------------------------------------------------------------
         //  Initialize the data structures for saving states at time "p"
         // --------------------------------------------------------------------
         self.left_qudits = vec![NULL_QUDIT; self.m];
         self.right_qudits = vec![NULL_QUDIT; self.m];
 
-        // --------------------------------------------------------
-        //  Initialize data structures related to operator string
-        // --------------------------------------------------------
-        self.op_string = vec![NULL_OP; self.m];    
-
-        self.v_first = vec![EMPTY; self.num_sites];     // for considering the two virtual bonds 
-        self.v_last = vec![EMPTY; self.num_sites];
-        self.vertex_list = vec![EMPTY; 4 * self.m];
+        // shared by the sites (vertex list) and the dual bonds (dual vertex list)
+        self.v_first = vec![EMPTY; self.num_sites.max(self.num_dual_bonds)];
+        self.v_last = vec![EMPTY; self.num_sites.max(self.num_dual_bonds)];
+        self.vertex_list = vec![EMPTY; self.num_legs * self.m];
   
         // ------------------------------------------------------------------------
         // Initialize for the internal stack (the capacity should be large enough)
         // ------------------------------------------------------------------------
-        self.stack = vec![0; 8 * self.m];
-
-        // -------------------------------------------
-        //  Decide the environment to be traced out
-        // -------------------------------------------
-        self.system = (0..self.num_sites).collect();
-        self.subsystem = (0..(self.num_sites / 2)).collect();
+        self.stack = vec![0; 2 * self.num_legs * self.m];
     }
-}
\ No newline at end of file
+}
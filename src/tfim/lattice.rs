@@ -0,0 +1,473 @@
+/*********************************************************************************
+    The lattice supplies four things to the engine
+        (i)   the sites
+        (ii)  the bonds "b_sites" carrying the ZZ couplings
+        (iii) the incidence of sites on the "dual bonds" used by the
+              bond (dual) cluster update
+        (iv)  a basis of the cycles of the bonds, for the winding update
+    -------------------------------------------------------------------
+    In the dual picture an off-diagonal site operator on site "s" may
+    only flip r^z of "s" an even number of times. Hence the dual bonds
+    touching "s" are grouped in pairs, and each pair is linked by the
+    site operator like the two bonds around a site of the 1D chain:
+        site_dual_bonds[s] = [b0, b1, b2, b3, ...]
+        pairs (b0, b1), (b2, b3), ...
+    The dual bonds are the physical bonds followed by "virtual" bonds,
+    which carry no operator and only close up sites of odd coordination
+    (e.g. the two ends of an open chain).
+    -------------------------------------------------------------------
+    Every dual bond flips r^z on two sites, so the parity of sum(r^z) is
+    conserved. This is exact for a (symmetric) ground state, but a mixed
+    state also has Bell samples of odd parity. For finite temperatures the
+    virtual bonds are replaced by "ghost" bonds attached to a single site,
+    whose flips change the parity (see "attach_ghost_bonds").
+    -------------------------------------------------------------------
+    Sites are labelled as s = x * ly + y, so that the first half of the
+    labels is the left half of the lattice.
+*********************************************************************************/
+use std::fmt;
+use std::str::FromStr;
+
+// ----------------------------------------------------------------
+//  Boundary conditions
+// ----------------------------------------------------------------
+/// The boundary conditions, "obc" or "pbc".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Open boundary conditions.
+    Open,
+    /// Periodic boundary conditions.
+    Periodic,
+}
+
+impl FromStr for Boundary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "obc" | "open" => Ok(Boundary::Open),
+            "pbc" | "periodic" => Ok(Boundary::Periodic),
+            _ => Err(format!("unknown boundary condition \"{}\" (expected \"obc\" or \"pbc\")", s)),
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Boundary::Open => write!(f, "OBC"),
+            Boundary::Periodic => write!(f, "PBC"),
+        }
+    }
+}
+
+// ----------------------------------------------------------------
+//  Supported geometries
+// ----------------------------------------------------------------
+/// The geometry of a lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatticeKind {
+    /// lx sites.
+    Chain,
+    /// lx rungs with ly legs, the boundary applies along the legs.
+    Ladder,
+    /// lx * ly sites.
+    Square,
+    /// lx * ly sites, the square lattice plus the (1, 1) diagonals.
+    Triangular,
+}
+
+impl FromStr for LatticeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chain" => Ok(LatticeKind::Chain),
+            "ladder" => Ok(LatticeKind::Ladder),
+            "square" => Ok(LatticeKind::Square),
+            "triangular" => Ok(LatticeKind::Triangular),
+            _ => Err(format!(
+                "unknown lattice \"{}\" (expected \"chain\", \"ladder\", \"square\" or \"triangular\")", s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LatticeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LatticeKind::Chain => write!(f, "chain"),
+            LatticeKind::Ladder => write!(f, "ladder"),
+            LatticeKind::Square => write!(f, "square"),
+            LatticeKind::Triangular => write!(f, "triangular"),
+        }
+    }
+}
+
+/// The sites and bonds of a lattice, with the dual bonds of the cluster updates.
+#[derive(Clone, Debug)]
+pub struct Lattice {
+    /// The geometry.
+    pub kind: LatticeKind,
+    /// The length along x.
+    pub lx: usize,
+    /// The width along y (1 for a chain).
+    pub ly: usize,
+    /// The boundary conditions.
+    pub boundary: Boundary,
+
+    /// The number of sites, lx * ly.
+    pub num_sites: usize,
+    /// The two sites of each physical bond.
+    pub b_sites: Vec<Vec<usize>>,
+    pub(crate) dual_b_sites: Vec<Vec<usize>>,      // physical bonds followed by the virtual (or ghost) ones
+    pub(crate) site_dual_bonds: Vec<Vec<usize>>,   // dual bonds around each site, paired two by two
+}
+
+impl Lattice {
+    /// The lattice of `kind`, with `ly` ignored for a chain.
+    pub fn new(kind: LatticeKind, lx: usize, ly: usize, boundary: Boundary) -> Result<Self, String> {
+        match kind {
+            LatticeKind::Chain => Self::chain(lx, boundary),
+            LatticeKind::Ladder => Self::ladder(lx, ly, boundary),
+            LatticeKind::Square => Self::square(lx, ly, boundary),
+            LatticeKind::Triangular => Self::triangular(lx, ly, boundary),
+        }
+    }
+
+    /// A chain of `l` sites.
+    pub fn chain(l: usize, boundary: Boundary) -> Result<Self, String> {
+        // ---------------------------------------------------------------------
+        //  Bond "b" links sites b and b + 1, and site "s" is paired with the
+        //  dual bonds (s - 1, s). For OBC the dual bond "l - 1" is virtual.
+        // ---------------------------------------------------------------------
+        let num_bonds = match boundary {
+            Boundary::Open if l < 2 => return Err(format!("OBC requires at least 2 sites, got l = {}", l)),
+            Boundary::Open => l - 1,
+            // for l = 2 the two bonds of the ring would coincide
+            Boundary::Periodic if l < 3 => return Err(format!("PBC requires at least 3 sites, got l = {}", l)),
+            Boundary::Periodic => l,
+        };
+
+        let b_sites: Vec<Vec<usize>> = (0..num_bonds).map(|b| vec![b, (b + 1) % l]).collect();
+        let dual_b_sites: Vec<Vec<usize>> = (0..l).map(|b| vec![b, (b + 1) % l]).collect();
+        let site_dual_bonds: Vec<Vec<usize>> = (0..l).map(|s| vec![(s + l - 1) % l, s]).collect();
+
+        Ok(Self {
+            kind: LatticeKind::Chain,
+            lx: l,
+            ly: 1,
+            boundary,
+            num_sites: l,
+            b_sites,
+            dual_b_sites,
+            site_dual_bonds,
+        })
+    }
+
+    /// A ladder of `lx` rungs and `ly` legs.
+    pub fn ladder(lx: usize, ly: usize, boundary: Boundary) -> Result<Self, String> {
+        if ly < 2 {
+            return Err(format!("A ladder requires at least 2 legs, got ly = {}", ly));
+        }
+        Self::check_length(lx, boundary, "lx")?;
+
+        let mut b_sites: Vec<Vec<usize>> = Vec::new();
+        for x in 0..lx {
+            for y in 0..ly {
+                if let Some(x1) = Self::next(x, lx, boundary) {
+                    b_sites.push(vec![x * ly + y, x1 * ly + y]);     // along the legs
+                }
+                if y + 1 < ly {
+                    b_sites.push(vec![x * ly + y, x * ly + y + 1]);  // rungs
+                }
+            }
+        }
+
+        Ok(Self::from_bonds(LatticeKind::Ladder, lx, ly, boundary, b_sites))
+    }
+
+    /// A square lattice of `lx * ly` sites.
+    pub fn square(lx: usize, ly: usize, boundary: Boundary) -> Result<Self, String> {
+        Self::check_length(lx, boundary, "lx")?;
+        Self::check_length(ly, boundary, "ly")?;
+
+        let mut b_sites: Vec<Vec<usize>> = Vec::new();
+        for x in 0..lx {
+            for y in 0..ly {
+                if let Some(x1) = Self::next(x, lx, boundary) {
+                    b_sites.push(vec![x * ly + y, x1 * ly + y]);
+                }
+                if let Some(y1) = Self::next(y, ly, boundary) {
+                    b_sites.push(vec![x * ly + y, x * ly + y1]);
+                }
+            }
+        }
+
+        Ok(Self::from_bonds(LatticeKind::Square, lx, ly, boundary, b_sites))
+    }
+
+    /// A triangular lattice of `lx * ly` sites.
+    pub fn triangular(lx: usize, ly: usize, boundary: Boundary) -> Result<Self, String> {
+        Self::check_length(lx, boundary, "lx")?;
+        Self::check_length(ly, boundary, "ly")?;
+
+        let mut b_sites: Vec<Vec<usize>> = Vec::new();
+        for x in 0..lx {
+            for y in 0..ly {
+                let x1 = Self::next(x, lx, boundary);
+                let y1 = Self::next(y, ly, boundary);
+                if let Some(x1) = x1 {
+                    b_sites.push(vec![x * ly + y, x1 * ly + y]);
+                }
+                if let Some(y1) = y1 {
+                    b_sites.push(vec![x * ly + y, x * ly + y1]);
+                }
+                if let (Some(x1), Some(y1)) = (x1, y1) {
+                    b_sites.push(vec![x * ly + y, x1 * ly + y1]);
+                }
+            }
+        }
+
+        Ok(Self::from_bonds(LatticeKind::Triangular, lx, ly, boundary, b_sites))
+    }
+
+    /// The number of physical bonds.
+    #[inline]
+    pub fn num_bonds(&self) -> usize {
+        self.b_sites.len()
+    }
+
+    #[inline]
+    pub(crate) fn num_dual_bonds(&self) -> usize {
+        self.dual_b_sites.len()
+    }
+
+    // the largest number of dual-bond pairs a site operator has to link
+    pub(crate) fn max_pairs(&self) -> usize {
+        self.site_dual_bonds.iter().map(|bonds| bonds.len() / 2).max().unwrap_or(1).max(1)
+    }
+
+    /// Translation average of a (row-major) site-site matrix along x:
+    ///     C(d) = mean of M[(x, y), (x + d, y)],  d = 0, ..., lx - 1
+    /// over the pairs inside the lattice (wrapped around for PBC).
+    pub fn translation_average(&self, matrix: &[f64]) -> Vec<f64> {
+        let n = self.num_sites;
+        (0..self.lx)
+            .map(|d| {
+                let mut sum: f64 = 0.0;
+                let mut count: usize = 0;
+                for x in 0..self.lx {
+                    let x1 = match self.boundary {
+                        Boundary::Open if x + d >= self.lx => continue,
+                        Boundary::Open => x + d,
+                        Boundary::Periodic => (x + d) % self.lx,
+                    };
+                    for y in 0..self.ly {
+                        sum += matrix[(x * self.ly + y) * n + x1 * self.ly + y];
+                        count += 1;
+                    }
+                }
+                sum / count as f64
+            })
+            .collect()
+    }
+
+    // ------------------------------------------------------------------------
+    //  Replace the virtual bonds by ghost bonds of a single site: each site of
+    //  odd coordination gets its own ghost, and if there is none, site 0 gets
+    //  two ghosts, each paired with one of its bonds.
+    // ------------------------------------------------------------------------
+    pub(crate) fn attach_ghost_bonds(&mut self) {
+        let num_bonds = self.num_bonds();
+        self.dual_b_sites.truncate(num_bonds);
+        for bonds in self.site_dual_bonds.iter_mut() {
+            bonds.retain(|&b| b < num_bonds);
+        }
+
+        let odd_sites: Vec<usize> = (0..self.num_sites).filter(|&s| self.site_dual_bonds[s].len() % 2 == 1).collect();
+        if odd_sites.is_empty() {
+            let g = self.dual_b_sites.len();
+            self.dual_b_sites.push(vec![0]);
+            self.dual_b_sites.push(vec![0]);
+            self.site_dual_bonds[0].insert(1, g);
+            self.site_dual_bonds[0].insert(3, g + 1);
+        }
+
+        for s in odd_sites {
+            let g = self.dual_b_sites.len();
+            self.dual_b_sites.push(vec![s]);
+            self.site_dual_bonds[s].push(g);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    //  A basis of the cycles of the physical bonds (mod 2), each cycle given
+    //  by its bonds. The shortest cycle through each bond and the cycles of a
+    //  spanning tree are the candidates, taken from the shortest on as long
+    //  as they are independent, so that the plaquettes come before the
+    //  cycles winding around a periodic lattice.
+    // ------------------------------------------------------------------------
+    pub(crate) fn cycles(&self) -> Vec<Vec<usize>> {
+        let num_bonds = self.num_bonds();
+        let mut neighbors: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.num_sites];
+        for (b, sites) in self.b_sites.iter().enumerate() {
+            neighbors[sites[0]].push((sites[1], b));
+            neighbors[sites[1]].push((sites[0], b));
+        }
+
+        // the bonds of a shortest path from "s0" to "s1" over the allowed bonds
+        let path = |s0: usize, s1: usize, allowed: &dyn Fn(usize) -> bool| -> Option<Vec<usize>> {
+            let mut from: Vec<Option<(usize, usize)>> = vec![None; self.num_sites];
+            let mut queue = std::collections::VecDeque::from([s0]);
+            while let Some(s) = queue.pop_front() {
+                if s == s1 {
+                    let mut bonds: Vec<usize> = Vec::new();
+                    let mut t = s1;
+                    while let Some((prev, b)) = from[t] {
+                        bonds.push(b);
+                        t = prev;
+                    }
+                    return Some(bonds);
+                }
+                for &(t, b) in neighbors[s].iter() {
+                    if allowed(b) && t != s0 && from[t].is_none() {
+                        from[t] = Some((s, b));
+                        queue.push_back(t);
+                    }
+                }
+            }
+            None
+        };
+
+        // a spanning tree (forest) by breadth-first search
+        let mut in_tree = vec![false; num_bonds];
+        let mut reached = vec![false; self.num_sites];
+        let mut num_components = 0;
+        for root in 0..self.num_sites {
+            if reached[root] {
+                continue;
+            }
+            num_components += 1;
+            reached[root] = true;
+            let mut queue = std::collections::VecDeque::from([root]);
+            while let Some(s) = queue.pop_front() {
+                for &(t, b) in neighbors[s].iter() {
+                    if !reached[t] {
+                        reached[t] = true;
+                        in_tree[b] = true;
+                        queue.push_back(t);
+                    }
+                }
+            }
+        }
+        let dimension = num_bonds + num_components - self.num_sites;
+
+        let mut candidates: Vec<Vec<usize>> = Vec::new();
+        for (b, sites) in self.b_sites.iter().enumerate() {
+            if let Some(mut bonds) = path(sites[0], sites[1], &|b1| b1 != b) {
+                bonds.push(b);
+                candidates.push(bonds);
+            }
+            if !in_tree[b] {
+                let mut bonds = path(sites[0], sites[1], &|b1| in_tree[b1]).expect("the tree spans the component");
+                bonds.push(b);
+                candidates.push(bonds);
+            }
+        }
+        for bonds in candidates.iter_mut() {
+            bonds.sort_unstable();
+        }
+        candidates.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        candidates.dedup();
+
+        // ------------------------------------------------------------------
+        //  Gaussian elimination mod 2 on bit sets of the bonds: a candidate
+        //  reduced by the rows of its lowest bits is independent if anything
+        //  is left, which then becomes the row of its lowest bit
+        // ------------------------------------------------------------------
+        let num_words = num_bonds.div_ceil(64);
+        let mut rows: Vec<Option<Vec<u64>>> = vec![None; num_bonds];
+        let mut basis: Vec<Vec<usize>> = Vec::with_capacity(dimension);
+        for bonds in candidates {
+            if basis.len() == dimension {
+                break;
+            }
+            let mut bits = vec![0u64; num_words];
+            for &b in bonds.iter() {
+                bits[b / 64] ^= 1 << (b % 64);
+            }
+            while let Some(w) = bits.iter().position(|&x| x != 0) {
+                let low = 64 * w + bits[w].trailing_zeros() as usize;
+                match rows[low].as_ref() {
+                    Some(row) => bits.iter_mut().zip(row).for_each(|(x, y)| *x ^= y),
+                    None => {
+                        rows[low] = Some(bits);
+                        basis.push(bonds);
+                        break;
+                    }
+                }
+            }
+        }
+        basis
+    }
+
+    // ------------------------------------------------------------------------
+    //  Attach the dual bonds to a list of physical bonds: the sites of odd
+    //  coordination are linked two by two by virtual bonds (a graph always
+    //  has an even number of them).
+    // ------------------------------------------------------------------------
+    fn from_bonds(kind: LatticeKind, lx: usize, ly: usize, boundary: Boundary, b_sites: Vec<Vec<usize>>) -> Self {
+        let num_sites = lx * ly;
+        let mut site_dual_bonds: Vec<Vec<usize>> = vec![Vec::new(); num_sites];
+        for (b, sites) in b_sites.iter().enumerate() {
+            site_dual_bonds[sites[0]].push(b);
+            site_dual_bonds[sites[1]].push(b);
+        }
+
+        let odd_sites: Vec<usize> = (0..num_sites).filter(|&s| site_dual_bonds[s].len() % 2 == 1).collect();
+        let mut dual_b_sites = b_sites.clone();
+        for pair in odd_sites.chunks(2) {
+            let b = dual_b_sites.len();
+            dual_b_sites.push(vec![pair[0], pair[1]]);
+            site_dual_bonds[pair[0]].push(b);
+            site_dual_bonds[pair[1]].push(b);
+        }
+
+        Self {
+            kind,
+            lx,
+            ly,
+            boundary,
+            num_sites,
+            b_sites,
+            dual_b_sites,
+            site_dual_bonds,
+        }
+    }
+
+    fn check_length(len: usize, boundary: Boundary, name: &str) -> Result<(), String> {
+        match boundary {
+            Boundary::Open if len < 2 => Err(format!("OBC requires {} >= 2, got {}", name, len)),
+            Boundary::Periodic if len < 3 => Err(format!("PBC requires {} >= 3, got {}", name, len)),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn next(i: usize, len: usize, boundary: Boundary) -> Option<usize> {
+        match boundary {
+            Boundary::Open => if i + 1 < len { Some(i + 1) } else { None },
+            Boundary::Periodic => Some((i + 1) % len),
+        }
+    }
+}
+
+impl fmt::Display for Lattice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            LatticeKind::Chain => write!(f, "1D chain (l = {}, {})", self.lx, self.boundary),
+            _ => write!(f, "{} lattice ({} x {}, {})", self.kind, self.lx, self.ly, self.boundary),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::tfim::TFIModel;
+use crate::tfim::{TFIModel, SimulationMode, Pauli, PauliPair, PauliString};
 /***********************************************************
  *      For s = (s^z, s^x), the Pauli matrix is 
  *              00 ~ I
@@ -12,15 +12,20 @@ impl TFIModel {
     // ==================================================
     const BASE: f64 = -1.0;
 
-    // #[inline]
-    // fn measure_sigma(&self, sigma: u8, s: usize) -> f64 {
-    //     assert!(sigma < 4, "Invalid Pauli matrix specified.");
-    //     let s_z: u8 = (sigma >> 1) & 1;
-    //     let s_x: u8 = sigma & 1;
-    //     let r_z: u8 = (self.qudits[s] >> 1 ) & 1;
-    //     let r_x: u8 = self.qudits[s] & 1;
-    //     Self::BASE.powf((s_x * r_z - s_z * r_x) as f64)
-    // }
+    // ------------------------------------------------------------------
+    //  sigma ⊗ sigma on the Bell pair r gives (-1) to the power of
+    //      s^x r^z + s^z r^x       (sigma anticommutes with sigma_r)
+    //      + s^x s^z               (Y^T = -Y)
+    // ------------------------------------------------------------------
+    #[inline]
+    fn measure_sigma(&self, sigma: Pauli, s: usize) -> f64 {
+        let code: u8 = sigma.code();
+        let s_z: u8 = (code >> 1) & 1;
+        let s_x: u8 = code & 1;
+        let r_z: u8 = (self.qudits[s] >> 1 ) & 1;
+        let r_x: u8 = self.qudits[s] & 1;
+        if (s_x & r_z) ^ (s_z & r_x) ^ (s_x & s_z) == 1 { -1.0 } else { 1.0 }
+    }
 
     #[inline]
     fn measure_x(&self, s: usize) -> f64 {
@@ -28,17 +33,10 @@ impl TFIModel {
         Self::BASE.powf(r_z as f64)
     }
 
-    // #[inline]
-    // fn measure_y(&self, s: usize) -> f64 {
-    //     let r_z: u8 = (self.qudits[s] >> 1 ) & 1;
-    //     let r_x: u8 = self.qudits[s] & 1;
-    //     Self::BASE.powf((r_z - r_x) as f64)
-    // }
-
     #[inline]
     fn measure_z(&self, s: usize) -> f64 {
         let r_x: u8 = self.qudits[s] & 1;
-        Self::BASE.powf(-1.0 * r_x as f64)
+        Self::BASE.powf(r_x as f64)
     }
 
     // ==================================================
@@ -66,37 +64,139 @@ impl TFIModel {
         self.measure_z(s_i) * self.measure_z(s_j)
     }    
 
-    
+    // the product of the signs of the factors
+    #[inline]
+    fn get_pauli_string_2(&self, pauli_string: &PauliString) -> f64 {
+        pauli_string.ops.iter().map(|&(s, sigma)| self.measure_sigma(sigma, s)).product()
+    }
+
+    #[inline]
+    fn get_pauli_corr_2(&self, sigma_i: Pauli, s_i: usize, sigma_j: Pauli, s_j: usize) -> f64 {
+        self.measure_sigma(sigma_i, s_i) * self.measure_sigma(sigma_j, s_j)
+    }
 }
 
 impl TFIModel {
+    /// Reset the accumulators of the observables before a bin.
     pub fn ini_measure(&mut self) {
+        self.num_steps = 0;
         self.purity = 0.0;
         self.partial_purity = 0.0;
+        self.complement_purity = 0.0;
+        for purity in self.region_purity.iter_mut() {
+            *purity = 0.0;
+        }
+        for purity in self.profile_purity.iter_mut() {
+            *purity = 0.0;
+        }
 
         for s in 0..self.num_sites {
             self.zz_corr_2[s] = 0.0;
             self.xx_corr_2[s] = 0.0;
         }
+        for corr in self.pauli_corr_2.iter_mut() {
+            for c in corr.iter_mut() {
+                *c = 0.0;
+            }
+        }
+        for c in self.pauli_string_2.iter_mut() {
+            *c = 0.0;
+        }
+        for c in self.zz_corr_matrix.iter_mut().chain(self.xx_corr_matrix.iter_mut()) {
+            *c = 0.0;
+        }
     }
 
-    pub fn measure(&mut self) {
+    pub(crate) fn measure(&mut self) {
         self.purity += self.measure_purity(&self.system);
         self.partial_purity += self.measure_purity(&self.subsystem);
+        if self.mode == SimulationMode::FiniteTemperature {
+            self.complement_purity += self.measure_purity(&self.complement);
+        }
+        for r in 0..self.regions.len() {
+            self.region_purity[r] += self.measure_purity(&self.regions[r].sites);
+        }
+
+        // the swap operator of [0, l) is the prefix product of the single-site ones
+        let mut swap: f64 = 1.0;
+        for l in 1..self.num_sites {
+            swap *= self.measure_swap(l - 1);
+            self.profile_purity[l - 1] += swap;
+        }
 
         for s in 0..self.num_sites {
-            self.zz_corr_2[s] += self.get_zz_corr_2(0, s);
-            self.xx_corr_2[s] += self.get_xx_corr2_(0, s);
+            self.zz_corr_2[s] += self.get_zz_corr_2(self.ref_site, s);
+            self.xx_corr_2[s] += self.get_xx_corr2_(self.ref_site, s);
+        }
+
+        for k in 0..self.pauli_pairs.len() {
+            let PauliPair(p, q) = self.pauli_pairs[k];
+            for s in 0..self.num_sites {
+                self.pauli_corr_2[k][s] += self.get_pauli_corr_2(p, self.ref_site, q, s);
+            }
+        }
+
+        for k in 0..self.pauli_strings.len() {
+            self.pauli_string_2[k] += self.get_pauli_string_2(&self.pauli_strings[k]);
+        }
+
+        // only the upper triangle, the lower one is filled in "statisticize"
+        if self.corr_matrix {
+            let n = self.num_sites;
+            for s in 0..n {
+                self.pauli_z[s] = self.measure_z(s);
+                self.pauli_x[s] = self.measure_x(s);
+            }
+            for i in 0..n {
+                for j in i..n {
+                    self.zz_corr_matrix[i * n + j] += self.pauli_z[i] * self.pauli_z[j];
+                    self.xx_corr_matrix[i * n + j] += self.pauli_x[i] * self.pauli_x[j];
+                }
+            }
         }
     }
 
-    pub fn statisticize(&mut self, num_samples: f64) {
+    /// Turn the accumulators into averages over the `num_stat * m` time slices
+    /// of the `num_stat` calls of [`TFIModel::mc_sampling`] since
+    /// [`TFIModel::ini_measure`]. The measurements ([`TFIModel::purity`],
+    /// [`TFIModel::zz_corr_2`], ...) then hold the bin averages.
+    pub fn statisticize(&mut self) {
+        let num_samples: f64 = self.num_steps as f64 * self.m as f64;    // use f64 to avoid the overflow
         self.purity /= num_samples;
         self.partial_purity /= num_samples;
+        self.complement_purity /= num_samples;
+        for purity in self.region_purity.iter_mut() {
+            *purity /= num_samples;
+        }
+        for purity in self.profile_purity.iter_mut() {
+            *purity /= num_samples;
+        }
 
         for s in 0..self.num_sites {
             self.zz_corr_2[s] /= num_samples;
             self.xx_corr_2[s] /= num_samples; 
         }
+
+        for corr in self.pauli_corr_2.iter_mut() {
+            for c in corr.iter_mut() {
+                *c /= num_samples;
+            }
+        }
+
+        for c in self.pauli_string_2.iter_mut() {
+            *c /= num_samples;
+        }
+
+        if self.corr_matrix {
+            let n = self.num_sites;
+            for i in 0..n {
+                for j in i..n {
+                    self.zz_corr_matrix[i * n + j] /= num_samples;
+                    self.xx_corr_matrix[i * n + j] /= num_samples;
+                    self.zz_corr_matrix[j * n + i] = self.zz_corr_matrix[i * n + j];
+                    self.xx_corr_matrix[j * n + i] = self.xx_corr_matrix[i * n + j];
+                }
+            }
+        }
     }
 }
\ No newline at end of file
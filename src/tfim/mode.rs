@@ -0,0 +1,57 @@
+/*********************************************************************************
+    Both modes sample Bell pairs from the two copies rho ⊗ rho with
+        rho = exp(-beta H) / Z
+    For a real H, (A ⊗ 1)|Phi> = (1 ⊗ A^T)|Phi> gives
+        (exp(-beta H) ⊗ exp(-beta H))|Phi> = (exp(-2 beta H) ⊗ 1)|Phi>
+    so the SSE always runs at the inverse temperature 2 * beta.
+    -------------------------------------------------------------------
+    GroundState:        beta is a projection length, large enough that
+                        rho is the ground state (purity = 1)
+    FiniteTemperature:  rho is the thermal state at beta, the purity is
+                        tr(rho^2) and the Renyi-2 mutual information
+                        I2(A:B) = S2(A) + S2(B) - S2(AB) is measured;
+                        the dual update uses ghost bonds to reach the Bell
+                        samples of odd r^z parity (see lattice.rs)
+*********************************************************************************/
+use std::fmt;
+use std::str::FromStr;
+
+/// What rho ⊗ rho samples, "gs" or "ft" on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimulationMode {
+    /// The ground state, projected out over a long enough beta.
+    GroundState,
+    /// The thermal state at beta.
+    FiniteTemperature,
+}
+
+impl SimulationMode {
+    /// The mode in words, for the report of a run.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SimulationMode::GroundState => "ground state simulation",
+            SimulationMode::FiniteTemperature => "finite temperature simulation",
+        }
+    }
+}
+
+impl FromStr for SimulationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gs" | "ground_state" => Ok(SimulationMode::GroundState),
+            "ft" | "finite_temperature" | "thermal" => Ok(SimulationMode::FiniteTemperature),
+            _ => Err(format!("unknown simulation mode \"{}\" (expected \"gs\" or \"ft\")", s)),
+        }
+    }
+}
+
+impl fmt::Display for SimulationMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationMode::GroundState => write!(f, "ground_state"),
+            SimulationMode::FiniteTemperature => write!(f, "finite_temperature"),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+/*********************************************************************************
+    Pauli operators in the (s^z, s^x) encoding of the qudits
+        00 ~ I,  01 ~ X,  10 ~ Z,  11 ~ Y
+    -------------------------------------------------------------------
+    A two-site correlator "PQ" is the squared expectation
+        tr(rho P_i Q_j)^2
+    between the reference site "i" and every site "j", e.g. "xy" or "zx".
+    -------------------------------------------------------------------
+    A Pauli string P gives tr(rho P)^2, written either sparse or dense:
+        X0 Z1 Z2 X3         Pauli and site, the other sites carry I
+        ZZZZ                one letter per site from site 0 ("I" allowed)
+    A Pauli strings file has one string per line ("#" starts a comment).
+*********************************************************************************/
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+/// A single-site Pauli operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pauli {
+    /// The identity.
+    I,
+    /// sigma^x.
+    X,
+    /// sigma^y.
+    Y,
+    /// sigma^z.
+    Z,
+}
+
+impl Pauli {
+    // the (s^z, s^x) encoding, like the qudits
+    #[inline]
+    pub(crate) fn code(&self) -> u8 {
+        match self {
+            Pauli::I => 0b00,
+            Pauli::X => 0b01,
+            Pauli::Z => 0b10,
+            Pauli::Y => 0b11,
+        }
+    }
+
+    /// The operator of a letter "I", "X", "Y" or "Z" (either case).
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'I' => Some(Pauli::I),
+            'X' => Some(Pauli::X),
+            'Y' => Some(Pauli::Y),
+            'Z' => Some(Pauli::Z),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Pauli {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pauli::I => write!(f, "I"),
+            Pauli::X => write!(f, "X"),
+            Pauli::Y => write!(f, "Y"),
+            Pauli::Z => write!(f, "Z"),
+        }
+    }
+}
+
+/// A two-site correlator "PQ": P on the reference site and Q on every site.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PauliPair(pub Pauli, pub Pauli);
+
+impl PauliPair {
+    /// The stem of the output file, e.g. "xy_corr_2" for "xy_corr_2.dat".
+    pub fn file_stem(&self) -> String {
+        format!("{}_corr_2", self.to_string().to_lowercase())
+    }
+
+    /// The pairs of "--corr xx,yy,xy", kept in order without duplicates.
+    pub fn parse_list(specs: &str) -> Result<Vec<Self>, String> {
+        let mut pairs: Vec<Self> = Vec::new();
+        for spec in specs.split(',').map(|spec| spec.trim()).filter(|spec| !spec.is_empty()) {
+            let pair: Self = spec.parse()?;
+            if !pairs.contains(&pair) {
+                pairs.push(pair);
+            }
+        }
+        Ok(pairs)
+    }
+}
+
+impl FromStr for PauliPair {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let paulis: Vec<Pauli> = s.chars().filter_map(Pauli::from_char).collect();
+        match paulis[..] {
+            [p, q] if s.chars().count() == 2 && p != Pauli::I && q != Pauli::I => Ok(PauliPair(p, q)),
+            _ => Err(format!("invalid correlator \"{}\" (expected two of \"x\", \"y\", \"z\", e.g. \"xy\")", s)),
+        }
+    }
+}
+
+impl fmt::Display for PauliPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.0, self.1)
+    }
+}
+
+/// A Pauli string P, whose tr(rho P)^2 is estimated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PauliString {
+    /// The string as given, e.g. "X0 Z1 Z2 X3".
+    pub label: String,
+    /// The non-identity factors (site, operator), sorted by site.
+    pub ops: Vec<(usize, Pauli)>,
+}
+
+impl PauliString {
+    /// A sparse ("X0 Z1") or dense ("XZ") Pauli string on `num_sites` sites.
+    pub fn parse(spec: &str, num_sites: usize) -> Result<Self, String> {
+        let label = spec.split_whitespace().collect::<Vec<_>>().join(" ");
+        if label.is_empty() {
+            return Err("empty Pauli string".to_string());
+        }
+        let error = |msg: &str| format!("Pauli string \"{}\": {}", label, msg);
+
+        let tokens: Vec<&str> = spec.split_whitespace().collect();
+        let mut ops: Vec<(usize, Pauli)> = Vec::new();
+        if tokens.len() == 1 && tokens[0].chars().all(|c| Pauli::from_char(c).is_some()) {
+            // dense, e.g. "ZZZZ"
+            if tokens[0].chars().count() > num_sites {
+                return Err(error(&format!("longer than the {} sites", num_sites)));
+            }
+            ops.extend(tokens[0].chars().enumerate().map(|(s, c)| (s, Pauli::from_char(c).unwrap())));
+        } else {
+            // sparse, e.g. "X0 Z1 Z2 X3"
+            for token in tokens.iter() {
+                let mut chars = token.chars();
+                let pauli = chars.next().and_then(Pauli::from_char).ok_or_else(|| error(&format!("invalid factor \"{}\"", token)))?;
+                let s: usize = chars.as_str().parse().map_err(|_| error(&format!("invalid factor \"{}\"", token)))?;
+                if s >= num_sites {
+                    return Err(error(&format!("site {} out of range (< {})", s, num_sites)));
+                }
+                if ops.iter().any(|&(t, _)| t == s) {
+                    return Err(error(&format!("site {} appears twice", s)));
+                }
+                ops.push((s, pauli));
+            }
+        }
+
+        ops.retain(|&(_, pauli)| pauli != Pauli::I);
+        ops.sort_unstable_by_key(|&(s, _)| s);
+        Ok(Self { label, ops })
+    }
+
+    /// The label without blanks, e.g. "X0_Z1_Z2_X3".
+    pub fn key(&self) -> String {
+        self.label.replace(' ', "_")
+    }
+
+    /// The Pauli strings of a file, one per line.
+    pub fn from_file(file_path: &str, num_sites: usize) -> Result<Vec<Self>, String> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("cannot read Pauli strings file \"{}\": {}", file_path, e))?;
+
+        let mut strings: Vec<Self> = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            strings.push(Self::parse(line, num_sites).map_err(|e| format!("{}:{}: {}", file_path, i + 1, e))?);
+        }
+        Ok(strings)
+    }
+}
+
+impl fmt::Display for PauliString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pairs() {
+        assert_eq!("xy".parse::<PauliPair>().unwrap(), PauliPair(Pauli::X, Pauli::Y));
+        assert_eq!("ZZ".parse::<PauliPair>().unwrap().file_stem(), "zz_corr_2");
+        let pairs = PauliPair::parse_list(" yy, xz ,yy,").unwrap();
+        assert_eq!(pairs, vec![PauliPair(Pauli::Y, Pauli::Y), PauliPair(Pauli::X, Pauli::Z)]);
+        for spec in ["x", "xyz", "xi", "ab", "x y"] {
+            assert!(spec.parse::<PauliPair>().is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_sparse_and_dense_strings() {
+        let sparse = PauliString::parse("  X0  Z3 Y1 ", 4).unwrap();
+        assert_eq!(sparse.label, "X0 Z3 Y1");
+        assert_eq!(sparse.key(), "X0_Z3_Y1");
+        assert_eq!(sparse.ops, vec![(0, Pauli::X), (1, Pauli::Y), (3, Pauli::Z)]);
+
+        let dense = PauliString::parse("XIZ", 4).unwrap();
+        assert_eq!(dense.ops, vec![(0, Pauli::X), (2, Pauli::Z)]);
+        assert_eq!(dense.to_string(), "XIZ");
+    }
+
+    #[test]
+    fn rejects_invalid_strings() {
+        for (spec, expected) in [
+            ("", "empty Pauli string"),
+            ("XZXZX", "Pauli string \"XZXZX\": longer than the 4 sites"),
+            ("X0 Q1", "Pauli string \"X0 Q1\": invalid factor \"Q1\""),
+            ("X0 Z", "Pauli string \"X0 Z\": invalid factor \"Z\""),
+            ("X4", "Pauli string \"X4\": site 4 out of range (< 4)"),
+            ("X0 Z0", "Pauli string \"X0 Z0\": site 0 appears twice"),
+        ] {
+            assert_eq!(PauliString::parse(spec, 4).unwrap_err(), expected, "{:?}", spec);
+        }
+    }
+}
@@ -2,22 +2,29 @@ use crate::tfim::TFIModel;
 
 impl TFIModel {
     #[inline]
-    pub fn rand_prob(&mut self) -> f64 {
-        (self.rng.next() % u32::MAX) as f64 / (u32::MAX as f64)
+    pub(crate) fn rand_prob(&mut self) -> f64 {
+        (self.rng.next_u32() % u32::MAX) as f64 / (u32::MAX as f64)
     }
 
+    // a bond "b" with probability J_b / sum(J)
     #[inline]
-    pub fn rand_bond(&mut self) -> usize {
-        self.rng.next() as usize % self.num_bonds
+    pub(crate) fn rand_bond(&mut self) -> usize {
+        let b = self.rng.next_u32() as usize % self.num_bonds;
+        if self.bond_table.prob[b] < 1.0 && self.rand_prob() >= self.bond_table.prob[b] {
+            self.bond_table.alias[b]
+        } else {
+            b
+        }
     }
 
+    // a site "i" with probability h_i / sum(h)
     #[inline]
-    pub fn rand_site(&mut self) -> usize { 
-        self.rng.next() as usize % self.num_sites 
-    }
-
-    #[inline]
-    pub fn rand_qudit(&mut self) -> u8 { 
-        (self.rng.next() as usize % 4) as u8 
+    pub(crate) fn rand_site(&mut self) -> usize { 
+        let s = self.rng.next_u32() as usize % self.num_sites;
+        if self.site_table.prob[s] < 1.0 && self.rand_prob() >= self.site_table.prob[s] {
+            self.site_table.alias[s]
+        } else {
+            s
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+/*********************************************************************************
+    Named regions of sites whose Renyi-2 entropies are measured
+    -------------------------------------------------------------------
+    A region is written as "name=items", the items being separated by
+    commas and each of them either a site or a half-open interval:
+        A=0..4              sites 0, 1, 2, 3
+        AB=0..2,6..8        a disjoint union
+        C=0,3,5             an arbitrary list of sites
+        D=2..=4             sites 2, 3, 4
+    A site given twice is an error. Several regions are separated by ";"
+    on the command line, and a regions file has one region per line ("#"
+    starts a comment).
+*********************************************************************************/
+use std::fmt;
+use std::fs;
+
+/// A named region of sites, whose purity is measured.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    /// The name, e.g. "A" for "renyi2_A".
+    pub name: String,
+    /// The sites, sorted, each of them once.
+    pub sites: Vec<usize>,
+}
+
+impl Region {
+    /// The region of `sites` (in any order), all below `num_sites` and
+    /// none of them given twice.
+    pub fn new(name: &str, mut sites: Vec<usize>, num_sites: usize) -> Result<Self, String> {
+        if name.is_empty() {
+            return Err("a region needs a name".to_string());
+        }
+        sites.sort_unstable();
+        if sites.is_empty() {
+            return Err(format!("region \"{}\" is empty", name));
+        }
+        if let Some(&s) = sites.iter().find(|&&s| s >= num_sites) {
+            return Err(format!("region \"{}\": site {} out of range (< {})", name, s, num_sites));
+        }
+        if let Some(pair) = sites.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("region \"{}\": site {} given twice", name, pair[0]));
+        }
+
+        Ok(Self { name: name.to_string(), sites })
+    }
+
+    /// A region written "name=items", e.g. "A=0..4".
+    pub fn parse(spec: &str, num_sites: usize) -> Result<Self, String> {
+        let (name, items) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("invalid region \"{}\" (expected \"name=items\")", spec))?;
+        let name = name.trim();
+
+        let mut sites: Vec<usize> = Vec::new();
+        for item in items.split(',').map(|item| item.trim()).filter(|item| !item.is_empty()) {
+            let error = || format!("region \"{}\": invalid item \"{}\"", name, item);
+            if let Some((a, b)) = item.split_once("..") {
+                let a: usize = a.trim().parse().map_err(|_| error())?;
+                let b: usize = match b.strip_prefix('=') {
+                    Some(b) => b.trim().parse::<usize>().map_err(|_| error())? + 1,
+                    None => b.trim().parse().map_err(|_| error())?,
+                };
+                sites.extend(a..b);
+            } else {
+                sites.push(item.parse().map_err(|_| error())?);
+            }
+        }
+
+        Self::new(name, sites, num_sites)
+    }
+
+    /// The regions of "A=0..4;B=0,2", with distinct names.
+    pub fn parse_list(specs: &str, num_sites: usize) -> Result<Vec<Self>, String> {
+        let regions: Vec<Self> = specs
+            .split(';')
+            .filter(|spec| !spec.trim().is_empty())
+            .map(|spec| Self::parse(spec, num_sites))
+            .collect::<Result<_, _>>()?;
+        Self::check_names(&regions)?;
+        Ok(regions)
+    }
+
+    /// The regions of a file, one per line, with distinct names.
+    pub fn from_file(file_path: &str, num_sites: usize) -> Result<Vec<Self>, String> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("cannot read regions file \"{}\": {}", file_path, e))?;
+
+        let mut regions: Vec<Self> = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            regions.push(Self::parse(line, num_sites).map_err(|e| format!("{}:{}: {}", file_path, i + 1, e))?);
+        }
+        Self::check_names(&regions)?;
+        Ok(regions)
+    }
+
+    /// An error if two of the regions have the same name.
+    pub fn check_names(regions: &[Self]) -> Result<(), String> {
+        for (i, region) in regions.iter().enumerate() {
+            if regions[..i].iter().any(|other| other.name == region.name) {
+                return Err(format!("region \"{}\" is defined twice", region.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sites: Vec<String> = self.sites.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}={}", self.name, sites.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_intervals_and_lists() {
+        assert_eq!(Region::parse("A=0..4", 8).unwrap().sites, vec![0, 1, 2, 3]);
+        assert_eq!(Region::parse("AB = 0..2, 6..8", 8).unwrap().sites, vec![0, 1, 6, 7]);
+        assert_eq!(Region::parse("C=5,0,3", 8).unwrap().sites, vec![0, 3, 5]);
+        assert_eq!(Region::parse("D=2..=4", 8).unwrap().sites, vec![2, 3, 4]);
+        let region = Region::parse(" E =1,7", 8).unwrap();
+        assert_eq!((region.name.as_str(), region.to_string()), ("E", "E=1,7".to_string()));
+    }
+
+    #[test]
+    fn parses_lists_of_regions() {
+        let regions = Region::parse_list("A=0..4;B=0,2;", 8).unwrap();
+        assert_eq!(regions.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn rejects_invalid_regions() {
+        for (spec, expected) in [
+            ("0..4", "invalid region \"0..4\" (expected \"name=items\")"),
+            ("=0..4", "a region needs a name"),
+            ("A=", "region \"A\" is empty"),
+            ("A=4..2", "region \"A\" is empty"),
+            ("A=0..x", "region \"A\": invalid item \"0..x\""),
+            ("A=-1", "region \"A\": invalid item \"-1\""),
+            ("A=0..9", "region \"A\": site 8 out of range (< 8)"),
+            ("A=8", "region \"A\": site 8 out of range (< 8)"),
+            ("C=5,0,3,3", "region \"C\": site 3 given twice"),
+            ("D=0..4,2..6", "region \"D\": site 2 given twice"),
+        ] {
+            assert_eq!(Region::parse(spec, 8).unwrap_err(), expected, "{:?}", spec);
+        }
+        assert_eq!(Region::parse_list("A=0;B=1;A=2", 8).unwrap_err(), "region \"A\" is defined twice");
+    }
+}
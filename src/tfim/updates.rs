@@ -1,24 +1,34 @@
 use crate::tfim::TFIModel;
-pub mod diagonal_update;
-pub mod make_vertex_list;
-pub mod cluster_update;
-pub mod bond_cluster_update;
+mod diagonal_update;
+mod make_vertex_list;
+mod cluster_update;
+mod bond_cluster_update;
+mod winding_update;
 use crate::aux::{EMPTY, NULL_QUDIT, NULL_OP};
 
 impl TFIModel {
+    /// One MC step of thermalization: the diagonal update, the cluster
+    /// updates of the sites and of the bonds, the winding update around the
+    /// cycles of the lattice, then a larger cut-off `m` if the expansion
+    /// order `n` has grown.
     pub fn mc_thermalizing(&mut self) {
         self.diag_update();  
         self.cluster_update(); 
         self.refresh_left_right_qudits();    
         self.bond_cluster_update();
+        self.winding_update();
         self.adjust_m(); 
     }
 
+    /// One MC step at a fixed cut-off, measuring every time slice of the
+    /// operator string into the accumulators (see [`TFIModel::ini_measure`]).
     pub fn mc_sampling(&mut self) {
+        self.num_steps += 1;
         self.diag_update_with_measure();   
         self.cluster_update(); 
         self.refresh_left_right_qudits();  
         self.bond_cluster_update();
+        self.winding_update();
     }
 
     fn adjust_m(&mut self) {
@@ -27,8 +37,8 @@ impl TFIModel {
         if self.m < new_m {
             self.op_string.extend(vec![NULL_OP; new_m - self.m]);
             self.m = new_m;
-            self.vertex_list = vec![EMPTY; 4 * new_m];
-            self.stack = vec![0; 8 * new_m];
+            self.vertex_list = vec![EMPTY; self.num_legs * new_m];
+            self.stack = vec![0; 2 * self.num_legs * new_m];
             self.left_qudits = vec![NULL_QUDIT; new_m];
             self.right_qudits = vec![NULL_QUDIT; new_m];     // "7" or "0b111" for invaild qudit
         }
@@ -1,11 +1,20 @@
 use crate::tfim::TFIModel;
-use crate::{flip_operator, flip_rz, flip_rx, get_rx};
+use crate::aux::{flip_operator, flip_rz, flip_rx, get_rx};
 use crate::aux::{EMPTY, FLIPPED, FREE_SPIN, NOT_FLIPPED};
 
 #[inline]
-fn to_back(v: usize) -> usize { v ^ 01 }
+fn to_back(v: usize) -> usize { v ^ 0b01 }
 
 impl TFIModel {
+    // flip r^z on the sites of the dual bond "b" (two sites, or one for a ghost bond)
+    #[inline]
+    fn flip_dual_bond(&mut self, b: usize) {
+        for i in 0..self.lattice.dual_b_sites[b].len() {
+            let s = self.lattice.dual_b_sites[b][i];
+            flip_rz!(self.qudits[s]);
+        }
+    }
+
     fn link_to_valid_dual_cluster_leg(&mut self, v: usize) -> i32 {
         // --------------------------------------------------
         //  It stops at 
@@ -25,7 +34,7 @@ impl TFIModel {
 
             loop {
                 v1 = self.vertex_list[v0] as usize;
-                the_p1 = v1 / 4;
+                the_p1 = v1 / self.num_legs;
                 op1 = self.op_string[the_p1];
                 remainder1 = (op1 % 4) as usize;
                 
@@ -43,7 +52,7 @@ impl TFIModel {
         }
     }
 
-    pub fn bond_cluster_update(&mut self) {
+    pub(crate) fn bond_cluster_update(&mut self) {
         self.make_dual_vertex_list();
 
         let mut op: i32;
@@ -53,7 +62,7 @@ impl TFIModel {
         let mut v0: usize;
         self.stack_initialize();        // for growing the cluster
 
-        for v in 0..4 * self.m {
+        for v in 0..self.num_legs * self.m {
             if self.vertex_list[v] < 0 { 
                 continue; 
             }
@@ -61,7 +70,7 @@ impl TFIModel {
             // ------------------------------------------------------------------------------
             // We must ensure it is a valid leg, i.e. it belongs to a valid bond operator 
             // ------------------------------------------------------------------------------
-            the_p = v / 4;
+            the_p = v / self.num_legs;
             op = self.op_string[the_p];
             remainder = (op % 4) as usize;
 
@@ -69,36 +78,34 @@ impl TFIModel {
             // an off-diagonal bond operator is always flippable
             // But for a diag-bond operator, we skip it if r^x != r^x
             // --------------------------------------------------------------------
-            if remainder >= 2 {
-                if (remainder == 3) || (get_rx!(self.left_qudits[the_p]) == get_rx!(self.right_qudits[the_p])) {
-                    self.flip = if self.rand_prob() > 0.5 { FLIPPED } else { NOT_FLIPPED };
-                    self.stack_push(v);
-                    
-                    // --------------------------------------------
-                    //  Check whether "v" acorss no site op
-                    // --------------------------------------------
-                    let v1: usize = self.link_to_valid_dual_cluster_leg(v) as usize;
-                    let the_p1: usize = v1 / 4;
-                    let op1: i32 = self.op_string[the_p1];
-                    let remainder1 = (op1 % 4) as usize;
-
-                    if remainder1 >= 2 {
-                        if self.flip == FLIPPED {
-                            self.op_string[the_p] = flip_operator!(self.op_string[the_p]);
-                            self.op_string[the_p1] = flip_operator!(self.op_string[the_p1]);
-                        }
-                        self.vertex_list[v] = self.flip;
-                        self.vertex_list[v1] = self.flip;
+            if remainder >= 2 && ((remainder == 3) || (get_rx!(self.left_qudits[the_p]) == get_rx!(self.right_qudits[the_p]))) {
+                self.flip = if self.rand_prob() > 0.5 { FLIPPED } else { NOT_FLIPPED };
+                self.stack_push(v);
+                
+                // --------------------------------------------
+                //  Check whether "v" acorss no site op
+                // --------------------------------------------
+                let v1: usize = self.link_to_valid_dual_cluster_leg(v) as usize;
+                let the_p1: usize = v1 / self.num_legs;
+                let op1: i32 = self.op_string[the_p1];
+                let remainder1 = (op1 % 4) as usize;
+
+                if remainder1 >= 2 {
+                    if self.flip == FLIPPED {
+                        flip_operator!(self.op_string[the_p]);
+                        flip_operator!(self.op_string[the_p1]);
                     }
+                    self.vertex_list[v] = self.flip;
+                    self.vertex_list[v1] = self.flip;
+                }
 
-                    // --------------------------------------------
-                    //  Grow the dual cluster starting with "v"
-                    // --------------------------------------------
-                    else {
-                        loop {
-                            if self.top == EMPTY { break; }
-                            self.make_bond_cluster();
-                        }
+                // --------------------------------------------
+                //  Grow the dual cluster starting with "v"
+                // --------------------------------------------
+                else {
+                    loop {
+                        if self.top == EMPTY { break; }
+                        self.make_bond_cluster();
                     }
                 }
             }
@@ -107,7 +114,7 @@ impl TFIModel {
         // ============================================================================
         //  Update the qudits at zero time after the updates of bond operators
         // ============================================================================
-        for b in 0..self.num_sites {
+        for b in 0..self.num_dual_bonds {
             if self.v_first[b] != FREE_SPIN {
                 let v = self.v_first[b] as usize;  
                 v0 = v;     // this is the start point
@@ -115,10 +122,7 @@ impl TFIModel {
                 loop {
                     if self.vertex_list[v0] < 0 {
                         if self.vertex_list[v0] == FLIPPED {
-                            let s_left = b;
-                            let s_right = (b + 1) % self.num_sites;
-                            self.qudits[s_left] = flip_rz!(self.qudits[s_left]);
-                            self.qudits[s_right] = flip_rz!(self.qudits[s_right]);
+                            self.flip_dual_bond(b);
                         }
                         break;
                          
@@ -136,10 +140,7 @@ impl TFIModel {
             else {
                 // Randomly change those isolated bonds (without changing the sector) 
                 if self.rand_prob() > 0.5 {
-                    let s_left = b;
-                    let s_right = (b + 1) % self.num_sites;
-                    self.qudits[s_left] = flip_rz!(self.qudits[s_left]);
-                    self.qudits[s_right] = flip_rz!(self.qudits[s_right]);
+                    self.flip_dual_bond(b);
                 }
             }
         }
@@ -149,16 +150,12 @@ impl TFIModel {
         // ============================================================================
         if self.rand_prob() > 0.5 {
             for s in 0..self.num_sites {
-                self.qudits[s] = flip_rx!(self.qudits[s]);
+                flip_rx!(self.qudits[s]);
             }
         }
     }
 
     fn make_bond_cluster(&mut self) {
-        let the_p: usize;
-        let op: i32;
-        let remainder: usize;
-
         let v_start: usize = self.stack_pop();
         let v1: i32 = self.link_to_valid_dual_cluster_leg(v_start);
 
@@ -183,16 +180,16 @@ impl TFIModel {
         // ::::::::::::::::::::::::::::::::::::::::
         //  Process this "v_start"
         // ::::::::::::::::::::::::::::::::::::::::
-        the_p = v_start / 4; 
-        op = self.op_string[the_p];
-        remainder = (op % 4) as usize;
+        let the_p: usize = v_start / self.num_legs;
+        let op: i32 = self.op_string[the_p];
+        let remainder: usize = (op % 4) as usize;
 
         // -----------------------------------------------------------------------------------------
         //  If this is a (valid) bond opeator, we flip it and the corresponding qudits
         // -----------------------------------------------------------------------------------------
         if remainder >= 2 {
             if self.flip == FLIPPED {
-                self.op_string[the_p] = flip_operator!(self.op_string[the_p]);  
+                flip_operator!(self.op_string[the_p]);  
             }
             
         }
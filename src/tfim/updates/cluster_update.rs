@@ -1,6 +1,6 @@
 use crate::tfim::TFIModel;
 use crate::aux::{FLIPPED, NOT_FLIPPED, EMPTY, FREE_SPIN};
-use crate::{flip_operator, flip_rx, get_rz, go_through};
+use crate::aux::{flip_operator, flip_rx, get_rz, go_through};
 
 impl TFIModel {
     fn link_to_valid_cluster_leg(&mut self, v: usize) -> i32 {
@@ -22,7 +22,7 @@ impl TFIModel {
 
             loop {
                 v1 = self.vertex_list[v0] as usize;
-                the_p1 = v1 / 4;
+                the_p1 = v1 / self.num_legs;
                 op1 = self.op_string[the_p1];
                 remainder1 = (op1 % 4) as usize;
                 
@@ -41,7 +41,7 @@ impl TFIModel {
         }
     }
     
-    pub fn cluster_update(&mut self) {
+    pub(crate) fn cluster_update(&mut self) {
         self.make_vertex_list();
 
         let mut op: i32;
@@ -51,7 +51,7 @@ impl TFIModel {
         let mut v0: usize;
         self.stack_initialize();        // for growing the cluster
 
-        for v in (0..4 * self.m).step_by(2) {
+        for v in (0..self.num_legs * self.m).step_by(2) {
             if self.vertex_list[v] < 0 { 
                 continue; 
             }
@@ -60,7 +60,7 @@ impl TFIModel {
             // We must ensure it is a valid leg, i.e. it belongs to 
             //  (i) a flippable site operator (ii) an off-diagonal bond operator
             // --------------------------------------------------------------------
-            the_p = v / 4;
+            the_p = v / self.num_legs;
             op = self.op_string[the_p];
             remainder = (op % 4) as usize;
 
@@ -68,18 +68,16 @@ impl TFIModel {
             // an off-diagonal site operator is always valid
             // But for a diag-site operator, we skip it if r^z = 1
             // --------------------------------------------------------------------
-            if remainder < 2 {
-                if (remainder == 1) || (get_rz!(self.left_qudits[the_p]) == 0) {
-                    self.flip = if self.rand_prob() > 0.5 { FLIPPED } else { NOT_FLIPPED };
-                    self.stack_push(v);
-
-                    // --------------------------------------------
-                    //  Grow the cluster starting with "v"
-                    // --------------------------------------------
-                    loop {
-                        if self.top == EMPTY { break; }
-                        self.make_cluster();
-                    }
+            if remainder < 2 && ((remainder == 1) || (get_rz!(self.left_qudits[the_p]) == 0)) {
+                self.flip = if self.rand_prob() > 0.5 { FLIPPED } else { NOT_FLIPPED };
+                self.stack_push(v);
+
+                // --------------------------------------------
+                //  Grow the cluster starting with "v"
+                // --------------------------------------------
+                loop {
+                    if self.top == EMPTY { break; }
+                    self.make_cluster();
                 }
             }            
         }
@@ -95,7 +93,7 @@ impl TFIModel {
                 loop {
                     if self.vertex_list[v0] < 0 {
                         if self.vertex_list[v0] == FLIPPED {
-                            self.qudits[s] = flip_rx!(self.qudits[s]);
+                            flip_rx!(self.qudits[s]);
                         }
                         break;
                     }
@@ -111,16 +109,12 @@ impl TFIModel {
 
             else {
                 // Randomly change those isolated qudits (without changing the sector)
-                if self.rand_prob() > 0.5 { self.qudits[s] = flip_rx!(self.qudits[s]); }
+                if self.rand_prob() > 0.5 { flip_rx!(self.qudits[s]); }
             }
         }
     }
 
     fn make_cluster(&mut self) {
-        let the_p: usize;
-        let op: i32;
-        let remainder: usize;
-
         let v_start: usize = self.stack_pop();
         let v1: i32 = self.link_to_valid_cluster_leg(v_start);
 
@@ -145,16 +139,16 @@ impl TFIModel {
         // ::::::::::::::::::::::::::::::::::::::::
         //  Process this "v_start"
         // ::::::::::::::::::::::::::::::::::::::::
-        the_p = v_start / 4; 
-        op = self.op_string[the_p];
-        remainder = (op % 4) as usize;
+        let the_p: usize = v_start / self.num_legs;
+        let op: i32 = self.op_string[the_p];
+        let remainder: usize = (op % 4) as usize;
 
         // -----------------------------------------------------
         //  If this is a (valid) site opeator, and we flip it 
         // -----------------------------------------------------
         if remainder < 2 {
             if self.flip == FLIPPED {
-                self.op_string[the_p] = flip_operator!(self.op_string[the_p]);
+                flip_operator!(self.op_string[the_p]);
             }     
         }
         
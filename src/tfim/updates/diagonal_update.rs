@@ -24,7 +24,7 @@ use crate::tfim::TFIModel;
 use crate::aux::{NULL_OP, NULL_QUDIT};
 
 impl TFIModel {
-    pub fn diag_update(&mut self) {
+    pub(crate) fn diag_update(&mut self) {
         let mut op: i32;
         let mut remainder: usize;
         let mut new_bond: usize;
@@ -59,8 +59,8 @@ impl TFIModel {
                         self.op_string[p] = (4 * new_bond + 2) as i32;
                         self.n += 1;
 
-                        the_qudit_left = self.qudits[self.b_sites[new_bond][0]];
-                        the_qudit_right = self.qudits[self.b_sites[new_bond][1]];
+                        the_qudit_left = self.qudits[self.lattice.b_sites[new_bond][0]];
+                        the_qudit_right = self.qudits[self.lattice.b_sites[new_bond][1]];
                     }
                 }
 
@@ -108,8 +108,8 @@ impl TFIModel {
 
                         else {
                             the_bond = (op / 4) as usize;
-                            the_qudit_left = self.qudits[self.b_sites[the_bond][0]];
-                            the_qudit_right = self.qudits[self.b_sites[the_bond][1]];
+                            the_qudit_left = self.qudits[self.lattice.b_sites[the_bond][0]];
+                            the_qudit_right = self.qudits[self.lattice.b_sites[the_bond][1]];
                         }
                     }
 
@@ -131,21 +131,21 @@ impl TFIModel {
                     // --------------------------------------------
                     3 => {
                         the_bond = (op / 4) as usize;
-                        the_qudit_left = self.qudits[self.b_sites[the_bond][0]];
-                        the_qudit_right = self.qudits[self.b_sites[the_bond][1]];
+                        the_qudit_left = self.qudits[self.lattice.b_sites[the_bond][0]];
+                        the_qudit_right = self.qudits[self.lattice.b_sites[the_bond][1]];
 
                         // The bond off-diagonal operator let
                         //      rz, rx --> (rz + 1) mod 2, rx 
                         // for the two sites it acts on
-                        self.qudits[self.b_sites[the_bond][0]] ^= 0b10;
-                        self.qudits[self.b_sites[the_bond][1]] ^= 0b10;
+                        self.qudits[self.lattice.b_sites[the_bond][0]] ^= 0b10;
+                        self.qudits[self.lattice.b_sites[the_bond][1]] ^= 0b10;
                     }
 
                     // --------------------------------------------
                     //  Others (errors)
                     // --------------------------------------------
                     _ => {
-                        panic!("Invalid bond occur with op_string[p] = {}", p);
+                        unreachable!("invalid operator {} at op_string[{}] (see check_op_string)", op, p);
                     }
                 }
             }
@@ -155,7 +155,7 @@ impl TFIModel {
         }
     }
 
-    pub fn refresh_left_right_qudits(&mut self) {
+    pub(crate) fn refresh_left_right_qudits(&mut self) {
         let mut op: i32;
         let mut remainder: usize;
 
@@ -193,8 +193,8 @@ impl TFIModel {
                     // ---------------------------------------
                     2 => {
                         the_bond = (op / 4) as usize;
-                        the_qudit_left = self.qudits[self.b_sites[the_bond][0]];
-                        the_qudit_right = self.qudits[self.b_sites[the_bond][1]];
+                        the_qudit_left = self.qudits[self.lattice.b_sites[the_bond][0]];
+                        the_qudit_right = self.qudits[self.lattice.b_sites[the_bond][1]];
                     }
 
                     // --------------------------------------------
@@ -215,14 +215,14 @@ impl TFIModel {
                     // --------------------------------------------
                     3 => {
                         the_bond = (op / 4) as usize;
-                        the_qudit_left = self.qudits[self.b_sites[the_bond][0]];
-                        the_qudit_right = self.qudits[self.b_sites[the_bond][1]];
+                        the_qudit_left = self.qudits[self.lattice.b_sites[the_bond][0]];
+                        the_qudit_right = self.qudits[self.lattice.b_sites[the_bond][1]];
 
                         // The bond off-diagonal operator let
                         //      rz, rx --> (rz + 1) mod 2, rx 
                         // for the two sites it acts on
-                        self.qudits[self.b_sites[the_bond][0]] ^= 0b10;
-                        self.qudits[self.b_sites[the_bond][1]] ^= 0b10;
+                        self.qudits[self.lattice.b_sites[the_bond][0]] ^= 0b10;
+                        self.qudits[self.lattice.b_sites[the_bond][1]] ^= 0b10;
 
                     }
 
@@ -230,7 +230,7 @@ impl TFIModel {
                     //  Others (errors)
                     // --------------------------------------------
                     _ => {
-                        panic!("Invalid bond occur with op_string[p] = {}", p);
+                        unreachable!("invalid operator {} at op_string[{}] (see check_op_string)", op, p);
                     }
                 }
             }
@@ -240,7 +240,7 @@ impl TFIModel {
         }
     }
 
-    pub fn diag_update_with_measure(&mut self) {
+    pub(crate) fn diag_update_with_measure(&mut self) {
         let mut op: i32;
         let mut remainder: usize;
         let mut new_bond: usize;
@@ -280,8 +280,8 @@ impl TFIModel {
                         self.op_string[p] = (4 * new_bond + 2) as i32;
                         self.n += 1;
 
-                        the_qudit_left = self.qudits[self.b_sites[new_bond][0]];
-                        the_qudit_right = self.qudits[self.b_sites[new_bond][1]];
+                        the_qudit_left = self.qudits[self.lattice.b_sites[new_bond][0]];
+                        the_qudit_right = self.qudits[self.lattice.b_sites[new_bond][1]];
                     }
                 }
 
@@ -329,8 +329,8 @@ impl TFIModel {
 
                         else {
                             the_bond = (op / 4) as usize;
-                            the_qudit_left = self.qudits[self.b_sites[the_bond][0]];
-                            the_qudit_right = self.qudits[self.b_sites[the_bond][1]];
+                            the_qudit_left = self.qudits[self.lattice.b_sites[the_bond][0]];
+                            the_qudit_right = self.qudits[self.lattice.b_sites[the_bond][1]];
                         }
                     }
 
@@ -352,21 +352,21 @@ impl TFIModel {
                     // --------------------------------------------
                     3 => {
                         the_bond = (op / 4) as usize;
-                        the_qudit_left = self.qudits[self.b_sites[the_bond][0]];
-                        the_qudit_right = self.qudits[self.b_sites[the_bond][1]];
+                        the_qudit_left = self.qudits[self.lattice.b_sites[the_bond][0]];
+                        the_qudit_right = self.qudits[self.lattice.b_sites[the_bond][1]];
 
                         // The bond off-diagonal operator let
                         //      rz, rx --> (rz + 1) mod 2, rx 
                         // for the two sites it acts on
-                        self.qudits[self.b_sites[the_bond][0]] ^= 0b10;
-                        self.qudits[self.b_sites[the_bond][1]] ^= 0b10;
+                        self.qudits[self.lattice.b_sites[the_bond][0]] ^= 0b10;
+                        self.qudits[self.lattice.b_sites[the_bond][1]] ^= 0b10;
                     }
 
                     // --------------------------------------------
                     //  Others (errors)
                     // --------------------------------------------
                     _ => {
-                        panic!("Invalid bond occur with op_string[p] = {}", p);
+                        unreachable!("invalid operator {} at op_string[{}] (see check_op_string)", op, p);
                     }
                 }
             }
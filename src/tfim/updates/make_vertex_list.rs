@@ -3,7 +3,7 @@ use crate::aux::{NULL_OP, EMPTY};
 
 
 impl TFIModel {
-    pub fn make_vertex_list(&mut self) {
+    pub(crate) fn make_vertex_list(&mut self) {
         let mut op: i32;
         let mut b_p: usize;
         let mut v_leg0: i32;
@@ -15,7 +15,7 @@ impl TFIModel {
         // ====================================================
         //  Initialize "vertex_list", "v_first", "v_last"
         // ====================================================
-        for v in 0..4 * self.m {
+        for v in 0..self.num_legs * self.m {
             self.vertex_list[v] = EMPTY;
         }
             
@@ -36,9 +36,9 @@ impl TFIModel {
                 // -.-.-.-.-.-.-.-.-.
                 if op % 4 >= 2 {
                     b_p = (op / 4) as usize;
-                    s0 = self.b_sites[b_p][0];
-                    s1 = self.b_sites[b_p][1];
-                    v_leg0 = (4 * p) as i32;
+                    s0 = self.lattice.b_sites[b_p][0];
+                    s1 = self.lattice.b_sites[b_p][1];
+                    v_leg0 = (self.num_legs * p) as i32;
 
                     s0_v_last = self.v_last[s0];
                     s1_v_last = self.v_last[s1];
@@ -71,7 +71,7 @@ impl TFIModel {
                 // -.-.-.-.-.-.-.-.-.
                 else {
                     s0 = (op / 4) as usize;
-                    v_leg0 = (4 * p) as i32;
+                    v_leg0 = (self.num_legs * p) as i32;
                     s0_v_last = self.v_last[s0];
 
                     if s0_v_last > EMPTY {
@@ -105,7 +105,7 @@ impl TFIModel {
         }
     }
 
-    pub fn make_dual_vertex_list(&mut self) {
+    pub(crate) fn make_dual_vertex_list(&mut self) {
         // Only the off-diagonal site operator is stretched
         let mut op: i32;
         let mut b_p: usize;
@@ -117,17 +117,18 @@ impl TFIModel {
 
         let mut b0_v_last: i32;
         let mut b1_v_last: i32;
+        let mut v_pair: i32;
 
         // ====================================================
         //  Initialize "vertex_list", "v_first", "v_last"
         // ====================================================
-        for v in 0..4 * self.m {
+        for v in 0..self.num_legs * self.m {
             self.vertex_list[v] = EMPTY;
         }
             
-        for s in 0..self.num_sites {
-            self.v_first[s] = EMPTY;
-            self.v_last[s] = EMPTY;
+        for b in 0..self.num_dual_bonds {
+            self.v_first[b] = EMPTY;
+            self.v_last[b] = EMPTY;
         }
 
         // ===================================
@@ -139,14 +140,14 @@ impl TFIModel {
             if op != NULL_OP {
                 b_p = (op / 4) as usize;
                 remainder = (op % 4) as usize;
-                v_leg0 = (4 * p) as i32;
+                v_leg0 = (self.num_legs * p) as i32;
 
                 match remainder {
                     2 | 3 => {
                         // -.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.
                         //  Bond operator
                         // -.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.
-                        b0 = self.b_sites[b_p][0];  // use the label of the left site for labelling the bond
+                        b0 = b_p;  // a physical bond keeps its label among the dual bonds
                         b0_v_last = self.v_last[b0];
 
                         if b0_v_last > EMPTY {
@@ -162,41 +163,45 @@ impl TFIModel {
                     }
 
                     0 | 1 => {
-                        // -.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.
-                        //  Off-diagonal site operator
-                        // -.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.
-                        b0 = (b_p - 1 + self.num_sites) % self.num_sites;
-                        b1 = b_p;
-
-                        {
-                            b0_v_last = self.v_last[b0 as usize];
-                            if b0_v_last > EMPTY {
-                                self.vertex_list[b0_v_last as usize] = v_leg0;
-                                self.vertex_list[v_leg0 as usize] = b0_v_last;
-                            } 
-
-                            else {
-                                self.v_first[b0 as usize] = v_leg0;
+                        // -.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.
+                        //  Site operator: link the k-th pair of dual bonds
+                        //  around the site with the legs 4k, ..., 4k + 3
+                        // -.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.-.
+                        for k in 0..self.lattice.site_dual_bonds[b_p].len() / 2 {
+                            b0 = self.lattice.site_dual_bonds[b_p][2 * k];
+                            b1 = self.lattice.site_dual_bonds[b_p][2 * k + 1];
+                            v_pair = v_leg0 + 4 * k as i32;
+
+                            {
+                                b0_v_last = self.v_last[b0];
+                                if b0_v_last > EMPTY {
+                                    self.vertex_list[b0_v_last as usize] = v_pair;
+                                    self.vertex_list[v_pair as usize] = b0_v_last;
+                                } 
+
+                                else {
+                                    self.v_first[b0] = v_pair;
+                                }
+
+                                self.v_last[b0] = v_pair + 1;
                             }
 
-                            self.v_last[b0 as usize] = v_leg0 + 1;
-                        }
+                            {
+                                b1_v_last = self.v_last[b1];
+                                if b1_v_last > EMPTY {
+                                    self.vertex_list[b1_v_last as usize] = v_pair + 2;
+                                    self.vertex_list[(v_pair + 2) as usize] = b1_v_last;
+                                } 
 
-                        {
-                            b1_v_last = self.v_last[b1];
-                            if b1_v_last > EMPTY {
-                                self.vertex_list[b1_v_last as usize] = v_leg0 + 2;
-                                self.vertex_list[(v_leg0 + 2) as usize] = b1_v_last;
-                            } 
+                                else {
+                                    self.v_first[b1] = v_pair + 2;
+                                }
 
-                            else {
-                                self.v_first[b1] = v_leg0 + 2;
+                                self.v_last[b1] = v_pair + 3;
                             }
-
-                            self.v_last[b1] = v_leg0 + 3;
                         }
                     }
-                    _ => { panic!(); }
+                    _ => unreachable!("invalid operator {} at op_string[{}] (see check_op_string)", op, p),
                 }
             }
         }
@@ -207,11 +212,11 @@ impl TFIModel {
         let mut s_v_first;
         let mut s_v_last;
 
-        for s in 0..self.num_sites {
-            s_v_first = self.v_first[s];
+        for b in 0..self.num_dual_bonds {
+            s_v_first = self.v_first[b];
             
             if s_v_first != EMPTY {
-                s_v_last = self.v_last[s];
+                s_v_last = self.v_last[b];
                 self.vertex_list[s_v_first as usize] = s_v_last;
                 self.vertex_list[s_v_last as usize] = s_v_first;
             }
@@ -0,0 +1,87 @@
+/*********************************************************************************
+    A cluster of the bond update holds an even number of legs of every
+    bond, so neither cluster update changes the parity of the number of
+    off-diagonal operators on a bond. On an open chain it has to be even,
+    but around a cycle of the lattice an odd number on every bond of the
+    cycle brings r^z back as well (each site of the cycle is flipped by
+    two of its bonds), and these configurations are never reached by the
+    cluster updates alone.
+    -------------------------------------------------------------------
+    For a cycle of the basis "cycles" (see lattice.rs), the winding update
+    takes the first flippable bond operator after the zero time on every
+    bond of the cycle, i.e. an off-diagonal one or a diagonal one with
+    r^x = r^x on its sites, and flips them all
+        diag <--> off-diag
+    Then r^z of a site of the cycle flips between the operators on its
+    two bonds of the cycle, which is allowed if no off-diagonal site
+    operator lies in between. All operators of a bond (site) have the same
+    weight, and the same operators are chosen after the flip, so the move
+    is its own inverse and is made with probability 1/2.
+*********************************************************************************/
+use crate::tfim::TFIModel;
+use crate::aux::{flip_operator, get_rx, NULL_OP};
+
+impl TFIModel {
+    pub(crate) fn winding_update(&mut self) {
+        if self.cycles.is_empty() {
+            return;
+        }
+
+        // ---------------------------------------------------------------------
+        //  One pass over the operators: the first flippable bond operator on
+        //  each bond and the off-diagonal site operators on each site, which
+        //  the flips around a cycle leave unchanged
+        // ---------------------------------------------------------------------
+        let mut first_ops: Vec<Option<usize>> = vec![None; self.num_bonds];
+        let mut site_ops: Vec<Vec<usize>> = vec![Vec::new(); self.num_sites];
+        for p in 0..self.m {
+            let op = self.op_string[p];
+            if op == NULL_OP {
+                continue;
+            }
+            let i = (op / 4) as usize;
+            match op % 4 {
+                1 => site_ops[i].push(p),
+                2 if get_rx!(self.left_qudits[p]) != get_rx!(self.right_qudits[p]) => {}
+                2 | 3 if first_ops[i].is_none() => first_ops[i] = Some(p),
+                _ => {}
+            }
+        }
+
+        for c in 0..self.cycles.len() {
+            if self.rand_prob() > 0.5 {
+                continue;
+            }
+            if let Some(positions) = self.winding_operators(c, &first_ops, &site_ops) {
+                for p in positions {
+                    flip_operator!(self.op_string[p]);
+                }
+            }
+        }
+    }
+
+    // the positions of the operators flipped around the cycle "c", if allowed
+    fn winding_operators(&self, c: usize, first_ops: &[Option<usize>], site_ops: &[Vec<usize>]) -> Option<Vec<usize>> {
+        let bonds = &self.cycles[c];
+        let positions: Vec<usize> = bonds.iter().map(|&b| first_ops[b]).collect::<Option<_>>()?;
+
+        // ----------------------------------------------------------------------
+        //  r^z of a site flips between the operators on its two bonds, where
+        //  an off-diagonal site operator must not be
+        // ----------------------------------------------------------------------
+        let mut ends: Vec<(usize, usize)> = Vec::with_capacity(2 * bonds.len());
+        for (&b, &p) in bonds.iter().zip(positions.iter()) {
+            ends.push((self.lattice.b_sites[b][0], p));
+            ends.push((self.lattice.b_sites[b][1], p));
+        }
+        ends.sort_unstable();
+        for pair in ends.chunks(2) {
+            let (s, start, end) = (pair[0].0, pair[0].1, pair[1].1);
+            let k = site_ops[s].partition_point(|&p| p < start);
+            if site_ops[s].get(k).is_some_and(|&p| p < end) {
+                return None;
+            }
+        }
+        Some(positions)
+    }
+}
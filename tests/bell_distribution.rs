@@ -0,0 +1,267 @@
+// Statistical validation of the sampler: the Bell samples ("qudits" at the
+// zero time) of short runs at L = 3, ..., 6 and on 3 x 3 square and
+// triangular lattices, at finite temperature and in the ground state, are
+// histogrammed and compared with the exact
+// distribution tr(rho P rho P) / 2^L of the exact diagonalization
+// (tr(rho P)^2 / 2^L for the ground state), by a chi-square test and the
+// total variation distance. A cluster update that violates
+// detailed balance distorts the histogram well beyond these bounds.
+//
+// The samples are taken every THINNING steps, several autocorrelation
+// times apart, and the seeds are fixed, so every test is deterministic.
+use bell_qmc_tfim_1d::ed::{self, ExactState};
+use bell_qmc_tfim_1d::tfim::{Boundary, Couplings, Lattice, LatticeKind, SimulationMode, TFIModel};
+
+const NUM_THM: usize = 2000;
+const THINNING: usize = 4;
+
+// the ground states of the 2D lattices decorrelate more slowly (the
+// periodic 3 x 3 lattices need a dozen steps or more between samples)
+const THINNING_2D_GROUND_STATE: usize = 16;
+
+// expected counts below this are pooled into a single bin of the chi-square test
+const MIN_EXPECTED: f64 = 5.0;
+
+// the chi-square statistic is rejected beyond dof + NUM_SIGMAS sqrt(2 dof)
+const NUM_SIGMAS: f64 = 5.0;
+
+struct Comparison {
+    chi_square: f64,
+    dof: usize,
+    total_variation: f64,
+    max_total_variation: f64,   // five times the expected distance of exact samples
+}
+
+impl Comparison {
+    fn accepted(&self) -> bool {
+        let dof = self.dof as f64;
+        self.chi_square < dof + NUM_SIGMAS * (2.0 * dof).sqrt() && self.total_variation < self.max_total_variation
+    }
+}
+
+// ------------------------------------------------------------------
+//  The histogram of "num_samples" Bell samples, indexed by ed::bell_index.
+//  The samples need no measurement, so the steps are those of the
+//  thermalization: the cut-off only grows, which leaves the sampled
+//  distribution unchanged.
+// ------------------------------------------------------------------
+fn sample(model: &mut TFIModel, num_samples: usize, thinning: usize) -> Vec<usize> {
+    model.init().unwrap();
+    for _ in 0..NUM_THM {
+        model.mc_thermalizing();
+    }
+    let mut counts = vec![0; 1 << (2 * model.num_sites())];
+    for _ in 0..num_samples {
+        for _ in 0..thinning {
+            model.mc_thermalizing();
+        }
+        counts[ed::bell_index(model.qudits())] += 1;
+    }
+    counts
+}
+
+fn compare(counts: &[usize], probabilities: &[f64]) -> Comparison {
+    let num_samples: usize = counts.iter().sum();
+    let n = num_samples as f64;
+
+    // samples of zero probability (e.g. odd parity in the ground state) never pass
+    let impossible: usize = counts.iter().zip(probabilities).filter(|&(_, &p)| p < 1e-14).map(|(&c, _)| c).sum();
+    if impossible > 0 {
+        return Comparison { chi_square: f64::INFINITY, dof: 1, total_variation: 1.0, max_total_variation: 0.0 };
+    }
+
+    let (mut chi_square, mut dof) = (0.0, 0usize);
+    let (mut pooled_count, mut pooled_expected) = (0.0, 0.0);
+    for (&c, &p) in counts.iter().zip(probabilities) {
+        let expected = n * p;
+        if expected < MIN_EXPECTED {
+            pooled_count += c as f64;
+            pooled_expected += expected;
+        } else {
+            chi_square += (c as f64 - expected).powi(2) / expected;
+            dof += 1;
+        }
+    }
+    if pooled_expected > 0.0 {
+        chi_square += (pooled_count - pooled_expected).powi(2) / pooled_expected;
+        dof += 1;
+    }
+
+    // E|c / n - p| ~ sqrt(2 p (1 - p) / (pi n)) for exact samples
+    let total_variation = 0.5 * counts.iter().zip(probabilities).map(|(&c, &p)| (c as f64 / n - p).abs()).sum::<f64>();
+    let expected_distance = 0.5
+        * probabilities.iter().map(|&p| (2.0 * p * (1.0 - p) / (std::f64::consts::PI * n)).sqrt()).sum::<f64>();
+    Comparison { chi_square, dof: dof - 1, total_variation, max_total_variation: 5.0 * expected_distance }
+}
+
+fn check(lattice: Lattice, couplings: Couplings, beta: f64, mode: SimulationMode, seed: u32, num_samples: usize) {
+    let exact = ExactState::new(&lattice, &couplings, beta, mode).unwrap();
+    let mut model = TFIModel::new(lattice.clone(), beta, couplings, seed, mode).unwrap();
+    let counts = sample(&mut model, num_samples, THINNING);
+    let result = compare(&counts, &exact.bell_distribution());
+    assert!(
+        result.accepted(),
+        "{} ({:?}, beta = {}): chi-square {:.1} for {} degrees of freedom, total variation {:.4} (at most {:.4})",
+        lattice, mode, beta, result.chi_square, result.dof, result.total_variation, result.max_total_variation
+    );
+}
+
+#[test]
+fn thermal_open_chain_l3() {
+    let lattice = Lattice::chain(3, Boundary::Open).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+    check(lattice, couplings, 0.5, SimulationMode::FiniteTemperature, 11, 40_000);
+}
+
+#[test]
+fn thermal_periodic_chain_l4() {
+    let lattice = Lattice::chain(4, Boundary::Periodic).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 0.8);
+    check(lattice, couplings, 1.0, SimulationMode::FiniteTemperature, 12, 40_000);
+}
+
+#[test]
+fn thermal_open_chain_l5_inhomogeneous() {
+    let lattice = Lattice::chain(5, Boundary::Open).unwrap();
+    let mut couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+    couplings.h = vec![0.6, 1.3, 0.9, 1.6, 0.4];
+    couplings.j = vec![1.2, 0.5, 1.0, 0.8];
+    check(lattice, couplings, 0.8, SimulationMode::FiniteTemperature, 13, 60_000);
+}
+
+#[test]
+fn thermal_periodic_chain_l6() {
+    let lattice = Lattice::chain(6, Boundary::Periodic).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+    check(lattice, couplings, 0.6, SimulationMode::FiniteTemperature, 14, 80_000);
+}
+
+#[test]
+fn thermal_ladder_3x2() {
+    let lattice = Lattice::new(LatticeKind::Ladder, 3, 2, Boundary::Open).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+    check(lattice, couplings, 0.5, SimulationMode::FiniteTemperature, 15, 80_000);
+}
+
+// ------------------------------------------------------------------
+//  The 2D lattices have 4^9 outcomes, too many for a histogram of the
+//  joint distribution, so the marginals of sets of sites are compared
+//  instead: the two sites of every bond (the pairing of the dual bonds),
+//  every row and column (the windings of a periodic lattice) and every
+//  2 x 2 plaquette (the shortest cycles of the cluster update).
+// ------------------------------------------------------------------
+fn marginal(values: &[f64], sites: &[usize]) -> Vec<f64> {
+    let mut m = vec![0.0; 1 << (2 * sites.len())];
+    for (index, &value) in values.iter().enumerate() {
+        let outcome = sites.iter().fold(0, |acc, &s| 4 * acc + ((index >> (2 * s)) & 0b11));
+        m[outcome] += value;
+    }
+    m
+}
+
+fn check_2d(lattice: Lattice, couplings: Couplings, beta: f64, mode: SimulationMode, seed: u32, num_samples: usize) {
+    let exact = ExactState::new(&lattice, &couplings, beta, mode).unwrap();
+    let probabilities = exact.bell_distribution();
+    let mut model = TFIModel::new(lattice.clone(), beta, couplings, seed, mode).unwrap();
+    let thinning = if mode == SimulationMode::GroundState { THINNING_2D_GROUND_STATE } else { THINNING };
+    let counts: Vec<f64> = sample(&mut model, num_samples, thinning).iter().map(|&c| c as f64).collect();
+
+    let (lx, ly) = (lattice.lx, lattice.ly);
+    let mut site_sets: Vec<Vec<usize>> = lattice.b_sites.clone();
+    site_sets.extend((0..lx).map(|x| (0..ly).map(|y| x * ly + y).collect()));
+    site_sets.extend((0..ly).map(|y| (0..lx).map(|x| x * ly + y).collect()));
+    for x in 0..lx - 1 {
+        for y in 0..ly - 1 {
+            site_sets.push(vec![x * ly + y, x * ly + y + 1, (x + 1) * ly + y, (x + 1) * ly + y + 1]);
+        }
+    }
+    for sites in site_sets.iter() {
+        let marginal_counts: Vec<usize> = marginal(&counts, sites).iter().map(|&c| c as usize).collect();
+        let result = compare(&marginal_counts, &marginal(&probabilities, sites));
+        assert!(
+            result.accepted(),
+            "{}, sites {:?}: chi-square {:.1} for {} degrees of freedom, total variation {:.4} (at most {:.4})",
+            lattice, sites, result.chi_square, result.dof, result.total_variation, result.max_total_variation
+        );
+    }
+}
+
+#[test]
+fn thermal_open_square_3x3() {
+    let lattice = Lattice::square(3, 3, Boundary::Open).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.5);
+    check_2d(lattice, couplings, 0.4, SimulationMode::FiniteTemperature, 21, 50_000);
+}
+
+#[test]
+fn thermal_periodic_square_3x3() {
+    let lattice = Lattice::square(3, 3, Boundary::Periodic).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 2.0);
+    check_2d(lattice, couplings, 0.3, SimulationMode::FiniteTemperature, 22, 50_000);
+}
+
+#[test]
+fn thermal_open_triangular_3x3() {
+    let lattice = Lattice::triangular(3, 3, Boundary::Open).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.5);
+    check_2d(lattice, couplings, 0.4, SimulationMode::FiniteTemperature, 23, 50_000);
+}
+
+#[test]
+fn thermal_periodic_triangular_3x3() {
+    let lattice = Lattice::triangular(3, 3, Boundary::Periodic).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 2.5);
+    check_2d(lattice, couplings, 0.3, SimulationMode::FiniteTemperature, 24, 50_000);
+}
+
+// the projection length makes the excited states negligible (gap ~ 1)
+#[test]
+fn ground_state_open_chain_l4() {
+    let lattice = Lattice::chain(4, Boundary::Open).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.5);
+    check(lattice, couplings, 6.0, SimulationMode::GroundState, 16, 20_000);
+}
+
+#[test]
+fn ground_state_periodic_chain_l6() {
+    let lattice = Lattice::chain(6, Boundary::Periodic).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+    check(lattice, couplings, 8.0, SimulationMode::GroundState, 17, 20_000);
+}
+
+// the ground states of the ladder and the 2D lattices, away from the critical
+// fields (h_c ~ 3.0 for the square lattice, ~ 4.8 for the triangular one):
+// the odd-degree sites of the open lattices are paired by virtual bonds
+// instead of the ghost bonds of finite temperature, and the periodic square
+// lattice has windings
+#[test]
+fn ground_state_ladder_3x2() {
+    let lattice = Lattice::new(LatticeKind::Ladder, 3, 2, Boundary::Open).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.5);
+    check(lattice, couplings, 6.0, SimulationMode::GroundState, 19, 20_000);
+}
+
+#[test]
+fn ground_state_periodic_square_3x3() {
+    let lattice = Lattice::square(3, 3, Boundary::Periodic).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 4.0);
+    check_2d(lattice, couplings, 2.5, SimulationMode::GroundState, 25, 6_000);
+}
+
+#[test]
+fn ground_state_open_triangular_3x3() {
+    let lattice = Lattice::triangular(3, 3, Boundary::Open).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 6.0);
+    check_2d(lattice, couplings, 2.0, SimulationMode::GroundState, 26, 6_000);
+}
+
+// the tests can tell: the samples at h = 1 reject the distribution at h = 1.3
+#[test]
+fn rejects_other_couplings() {
+    let lattice = Lattice::chain(4, Boundary::Periodic).unwrap();
+    let couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+    let other = ExactState::thermal(&lattice, &Couplings::uniform(&lattice, 1.0, 1.3), 1.0).unwrap();
+    let mut model = TFIModel::new(lattice, 1.0, couplings, 18, SimulationMode::FiniteTemperature).unwrap();
+    let counts = sample(&mut model, 40_000, THINNING);
+    assert!(!compare(&counts, &other.bell_distribution()).accepted());
+}
@@ -0,0 +1,220 @@
+// A run stopped after some bins (or during the thermalization) and resumed
+// from its checkpoint has to be
+// indistinguishable from an uninterrupted one: the checkpoint restores the
+// configuration, the PRNG state and the accumulated statistics, and the
+// appended ".dat" files are truncated back to their lengths at the save.
+// The same holds for a sweep, a disorder average or parallel chains stopped
+// during their runs and run again.
+use std::fs;
+use std::path::{Path, PathBuf};
+use bell_qmc_tfim_1d::config::SimulationConfig;
+use bell_qmc_tfim_1d::driver::{self, Control, Schedule};
+use bell_qmc_tfim_1d::tfim::{Boundary, Couplings, Lattice, LatticeKind, Region, SimulationMode, TFIModel};
+
+const NUM_THM: usize = 200;
+const NUM_STAT: usize = 100;
+
+fn model() -> TFIModel {
+    model_with(|_| {})
+}
+
+// the model of the tests, with its couplings changed by "change"
+fn model_with(change: impl FnOnce(&mut Couplings)) -> TFIModel {
+    let lattice = Lattice::new(LatticeKind::Chain, 6, 1, Boundary::Periodic).unwrap();
+    let mut couplings = Couplings::uniform(&lattice, 1.0, 0.8);
+    change(&mut couplings);
+    let mut model = TFIModel::new(lattice, 1.0, couplings, 2024, SimulationMode::FiniteTemperature).unwrap();
+    model.init().unwrap();
+    model.set_corr_matrix(true);
+    model
+}
+
+fn target_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bell_qmc_checkpoint_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(target_dir: &Path, num_bins: usize, control: Control) -> usize {
+    let schedule = Schedule { num_thm: NUM_THM, num_stat: NUM_STAT, num_bins };
+    let control = Control { quiet: true, ..control };
+    driver::run(&mut model(), schedule, target_dir.to_str().unwrap(), control).unwrap().records.len()
+}
+
+// the ".dat" files of a directory, by name
+fn dat_files(dir: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut files: Vec<(String, Vec<u8>)> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dat"))
+        .map(|path| (path.file_name().unwrap().to_string_lossy().into_owned(), fs::read(&path).unwrap()))
+        .collect();
+    files.sort();
+    files
+}
+
+fn assert_same_dat_files(expected: &Path, actual: &Path) {
+    let expected = dat_files(expected);
+    let actual = dat_files(actual);
+    assert!(expected.iter().any(|(name, _)| name == "zz_corr_2_dist.dat"));
+    assert_eq!(
+        expected.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        actual.iter().map(|(name, _)| name).collect::<Vec<_>>()
+    );
+    for ((name, expected), (_, actual)) in expected.iter().zip(&actual) {
+        assert!(expected == actual, "\"{}\" differs after resuming", name);
+    }
+}
+
+#[test]
+fn resumed_run_matches_uninterrupted_run() {
+    let uninterrupted = target_dir("uninterrupted");
+    run(&uninterrupted, 6, Control::default());
+
+    // 3 bins, then 3 more from the checkpoint of a fresh model
+    let resumed = target_dir("resumed");
+    let checkpoint_path = resumed.join("checkpoint.bin");
+    let control = Control { checkpoint_path: checkpoint_path.to_str(), ..Control::default() };
+    assert_eq!(run(&resumed, 3, control), 3);
+    assert_eq!(run(&resumed, 6, control), 6);
+    assert_same_dat_files(&uninterrupted, &resumed);
+
+    fs::remove_dir_all(&uninterrupted).unwrap();
+    fs::remove_dir_all(&resumed).unwrap();
+}
+
+#[test]
+fn run_stopped_during_thermalization_resumes() {
+    let uninterrupted = target_dir("uninterrupted_thm");
+    run(&uninterrupted, 6, Control::default());
+
+    // the STOP file is found after the first steps of the thermalization
+    let resumed = target_dir("resumed_thm");
+    fs::write(resumed.join("STOP"), "").unwrap();
+    let control = Control { stop_file: true, ..Control::default() };
+    assert_eq!(run(&resumed, 6, control), 0);
+    assert!(!resumed.join("STOP").exists());
+
+    let checkpoint_path = resumed.join("checkpoint.bin");
+    assert!(checkpoint_path.exists());
+    assert_eq!(run(&resumed, 6, Control { checkpoint_path: checkpoint_path.to_str(), ..control }), 6);
+    assert_same_dat_files(&uninterrupted, &resumed);
+
+    fs::remove_dir_all(&uninterrupted).unwrap();
+    fs::remove_dir_all(&resumed).unwrap();
+}
+
+#[test]
+fn checkpoint_of_other_measurements_is_rejected() {
+    let schedule = Schedule { num_thm: NUM_THM, num_stat: NUM_STAT, num_bins: 2 };
+    let with_region = |sites: Vec<usize>| {
+        let mut model = model();
+        model.set_regions(vec![Region::new("A", sites, 6).unwrap()]).unwrap();
+        model
+    };
+
+    // one bin measuring region A = {0, 1} with the reference site 0
+    let dir = target_dir("other_measurements");
+    let checkpoint_path = dir.join("checkpoint.bin");
+    let control = Control { checkpoint_path: checkpoint_path.to_str(), quiet: true, ..Control::default() };
+    let dir_str = dir.to_str().unwrap();
+    assert_eq!(driver::run(&mut with_region(vec![0, 1]), Schedule { num_bins: 1, ..schedule }, dir_str, control).unwrap().records.len(), 1);
+
+    // the same numbers of regions and correlators, but other ones
+    let mut other_ref_site = with_region(vec![0, 1]);
+    other_ref_site.set_ref_site(2).unwrap();
+    assert!(driver::run(&mut other_ref_site, schedule, dir_str, control).is_err());
+    assert!(driver::run(&mut with_region(vec![0, 2]), schedule, dir_str, control).is_err());
+    assert_eq!(driver::run(&mut with_region(vec![0, 1]), schedule, dir_str, control).unwrap().records.len(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checkpoint_of_other_couplings_is_rejected() {
+    let schedule = Schedule { num_thm: NUM_THM, num_stat: NUM_STAT, num_bins: 2 };
+    let dir = target_dir("other_couplings");
+    let checkpoint_path = dir.join("checkpoint.bin");
+    let control = Control { checkpoint_path: checkpoint_path.to_str(), quiet: true, ..Control::default() };
+    let dir_str = dir.to_str().unwrap();
+    assert_eq!(driver::run(&mut model(), Schedule { num_bins: 1, ..schedule }, dir_str, control).unwrap().records.len(), 1);
+
+    // a single bond or site differs, by as little as a rounding error
+    assert!(driver::run(&mut model_with(|c| c.j[3] = 1.1), schedule, dir_str, control).is_err());
+    assert!(driver::run(&mut model_with(|c| c.h[0] = 0.8 + 1e-15), schedule, dir_str, control).is_err());
+    assert_eq!(driver::run(&mut model(), schedule, dir_str, control).unwrap().records.len(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// a run of the command line into "target_dir", and its manifest
+fn run_command_line(line: &str, target_dir: &Path) -> String {
+    let mut args: Vec<String> = line.split_whitespace().map(String::from).collect();
+    args.extend(["--output".to_string(), target_dir.to_str().unwrap().to_string()]);
+    bell_qmc_tfim_1d::run(&SimulationConfig::from_args(&args).unwrap()).unwrap();
+    fs::read_to_string(target_dir.join("run.json")).unwrap()
+}
+
+// the value of "key" in a manifest, as written (a string keeps its quotes)
+fn value<'a>(manifest: &'a str, key: &str) -> &'a str {
+    let pattern = format!("\"{}\": ", key);
+    let start = manifest.find(&pattern).unwrap() + pattern.len();
+    manifest[start..].split([',', '\n']).next().unwrap()
+}
+
+// the ".dat" files of "expected" and "actual" and of their run directories
+fn assert_same_runs(expected: &Path, actual: &Path, run_dirs: &[&str]) {
+    assert_eq!(dat_files(expected), dat_files(actual));
+    for run_dir in run_dirs.iter() {
+        let expected_files = dat_files(&expected.join(run_dir));
+        assert!(expected_files.iter().any(|(name, _)| name == "purity.dat"));
+        assert!(expected_files == dat_files(&actual.join(run_dir)), "\"{}\" differs after resuming", run_dir);
+    }
+}
+
+// the STOP file is found during the thermalization of the first run
+// (with 200 thermalization steps and 3 bins of 50 steps in every run)
+fn assert_stopped_run_resumes(name: &str, line: &str, run_dirs: &[&str]) {
+    let num_runs = run_dirs.len();
+    let uninterrupted = target_dir(&format!("uninterrupted_{}", name));
+    let manifest = run_command_line(line, &uninterrupted);
+    assert_eq!(value(&manifest, "status"), "\"completed\"");
+
+    let resumed = target_dir(&format!("resumed_{}", name));
+    fs::write(resumed.join("STOP"), "").unwrap();
+    let manifest = run_command_line(line, &resumed);
+    assert_eq!(value(&manifest, "status"), "\"stopped\"");
+    assert!(value(&manifest, "num_bins_done").parse::<usize>().unwrap() < 3 * num_runs);
+    assert!(value(&manifest, "thermalization_steps").parse::<usize>().unwrap() < 200 * num_runs);
+    assert!(!resumed.join("STOP").exists());
+    assert!(resumed.join(run_dirs[0]).join("checkpoint.bin").exists());
+
+    let manifest = run_command_line(line, &resumed);
+    assert_eq!(value(&manifest, "status"), "\"completed\"");
+    assert_eq!(value(&manifest, "num_bins_done"), (3 * num_runs).to_string());
+    assert_eq!(value(&manifest, "thermalization_steps"), (200 * num_runs).to_string());
+    assert_eq!(value(&manifest, "sampling_steps"), (150 * num_runs).to_string());
+    assert_same_runs(&uninterrupted, &resumed, run_dirs);
+
+    fs::remove_dir_all(&uninterrupted).unwrap();
+    fs::remove_dir_all(&resumed).unwrap();
+}
+
+#[test]
+fn stopped_sweep_resumes() {
+    let line = "--l 4 --beta 1 --j 1 --h 1 --mode ft --num-thm 200 --num-stat 50 --num-bins 3 --seed 11 --sweep h=0.8,1,1.2";
+    assert_stopped_run_resumes("sweep", line, &["point_0", "point_1", "point_2"]);
+}
+
+#[test]
+fn stopped_disorder_average_resumes() {
+    let line = "--l 4 --beta 1 --j 1 --h 1 --mode ft --num-thm 200 --num-stat 50 --num-bins 3 --seed 11 --disorder box --realizations 2";
+    assert_stopped_run_resumes("disorder", line, &["realization_0", "realization_1"]);
+}
+
+#[test]
+fn stopped_chains_resume() {
+    let line = "--l 4 --beta 1 --j 1 --h 1 --mode ft --num-thm 200 --num-stat 50 --num-bins 3 --seed 11 --chains 3";
+    assert_stopped_run_resumes("chains", line, &["chain_0", "chain_1", "chain_2"]);
+}
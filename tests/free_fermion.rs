@@ -0,0 +1,61 @@
+// The free-fermion ground state of the chain against exact diagonalization:
+// at L = 6, ..., 10, open and periodic, in the ordered (h < J) and the
+// disordered (h > J) phases, the energy, the squared correlators
+// tr(rho Z_r Z_j)^2 and tr(rho X_r X_j)^2 and the purities of the blocks
+// [0, l) have to agree to rounding. A sign of the Majorana covariance
+// matrix, or the wrong fermion parity sector of a periodic chain, shows up
+// in the energy or the correlators.
+use bell_qmc_tfim_1d::ed::ExactState;
+use bell_qmc_tfim_1d::free_fermion::MajoranaState;
+use bell_qmc_tfim_1d::tfim::{Boundary, Couplings, Lattice, Pauli};
+
+const TOLERANCE: f64 = 1e-8;
+
+fn assert_close(what: &str, context: &str, expected: &[f64], actual: &[f64]) {
+    assert_eq!(expected.len(), actual.len());
+    for (k, (e, a)) in expected.iter().zip(actual).enumerate() {
+        assert!((e - a).abs() < TOLERANCE, "{}: {}[{}] = {} from the fermions, {} exactly", context, what, k, a, e);
+    }
+}
+
+fn check(lattice: Lattice, couplings: Couplings, description: &str) {
+    let context = format!("{}, {}", lattice, description);
+    let exact = ExactState::ground_state(&lattice, &couplings).unwrap();
+    let fermions = MajoranaState::ground_state(&lattice, &couplings).unwrap();
+    let num_sites = lattice.num_sites;
+    assert_close("energy", &context, &[exact.energy], &[fermions.energy]);
+
+    let square = |values: Vec<f64>| -> Vec<f64> { values.into_iter().map(|x| x * x).collect() };
+    for r in [0, num_sites / 2, num_sites - 1] {
+        assert_close(&format!("zz_corr_2 (r = {})", r), &context, &exact.corr_2(Pauli::Z, Pauli::Z, r), &square(fermions.zz_corr(r)));
+        assert_close(&format!("xx_corr_2 (r = {})", r), &context, &exact.corr_2(Pauli::X, Pauli::X, r), &square(fermions.xx_corr(r)));
+    }
+
+    let sites: Vec<usize> = (0..num_sites).collect();
+    let purities: Vec<f64> = (1..=num_sites).map(|l| exact.region_purity(&sites[..l])).collect();
+    assert_close("block purity", &context, &purities, &fermions.block_purities());
+}
+
+#[test]
+fn uniform_chains() {
+    for boundary in [Boundary::Open, Boundary::Periodic] {
+        for l in 6..=10 {
+            for h in [0.5, 1.0, 1.5] {
+                let lattice = Lattice::chain(l, boundary).unwrap();
+                let couplings = Couplings::uniform(&lattice, 1.0, h);
+                check(lattice, couplings, &format!("h = {}", h));
+            }
+        }
+    }
+}
+
+#[test]
+fn inhomogeneous_chains() {
+    for boundary in [Boundary::Open, Boundary::Periodic] {
+        let lattice = Lattice::chain(8, boundary).unwrap();
+        let mut couplings = Couplings::uniform(&lattice, 1.0, 1.0);
+        couplings.h = vec![0.6, 1.3, 0.9, 1.6, 0.4, 1.1, 0.7, 1.2];
+        couplings.j.iter_mut().zip([1.2, 0.5, 1.0, 0.8, 1.4, 0.9, 1.1, 0.6]).for_each(|(j, x)| *j = x);
+        check(lattice, couplings, "inhomogeneous");
+    }
+}